@@ -0,0 +1,228 @@
+// Copyright 2020 Eishun Kondoh
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::auth::paths;
+use crate::error::MistError;
+use crate::http::HttpClient;
+use crate::logger::{debug, warn};
+use crate::output::Render;
+use crate::types::{OrgId, SiteId};
+use serde::{Deserialize, Serialize};
+
+///
+/// ref: https://api.mist.com/api/v1/docs/Org#audit-logs
+///
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct AuditLogs(Vec<AuditLog>);
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AuditLog {
+    #[serde(default)]
+    pub id: Option<String>,
+    // epoch seconds
+    pub timestamp: f64,
+    // "<name> <email>" of the admin who made the change
+    #[serde(default)]
+    pub admin_name: Option<String>,
+    #[serde(default)]
+    pub admin_id: Option<String>,
+    pub message: String,
+    #[serde(default)]
+    pub org_id: Option<OrgId>,
+    #[serde(default)]
+    pub site_id: Option<SiteId>,
+    #[serde(default)]
+    pub src_ip: Option<String>,
+    // payload before and after the change
+    #[serde(default)]
+    pub before: Option<serde_json::Value>,
+    #[serde(default)]
+    pub after: Option<serde_json::Value>,
+}
+
+#[derive(Serialize, Debug, Default, Clone)]
+pub struct AuditLogQuery {
+    // epoch seconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub site_id: Option<SiteId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub admin_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+}
+
+/// Lazy iterator over the pages of audit logs, the API reports the
+/// number of matching logs in the body instead of headers
+pub struct AuditLogPages<'a> {
+    c: &'a HttpClient,
+    path: String,
+    query: AuditLogQuery,
+    fetched: u64,
+    done: bool,
+}
+
+#[derive(Deserialize, Debug)]
+struct AuditLogPage {
+    #[serde(default)]
+    total: Option<u64>,
+    #[serde(default)]
+    results: Vec<AuditLog>,
+}
+
+impl AuditLog {
+    /// email part of `admin_name`, when there is one
+    pub fn admin_email(&self) -> Option<&str> {
+        self.admin_name
+            .as_deref()?
+            .split_whitespace()
+            .find(|word| word.contains('@'))
+    }
+}
+
+impl From<Vec<AuditLog>> for AuditLogs {
+    fn from(v: Vec<AuditLog>) -> Self {
+        AuditLogs(v)
+    }
+}
+
+impl Render for AuditLogs {
+    type Record = AuditLog;
+
+    fn default_columns() -> &'static [&'static str] {
+        &["timestamp", "admin_name", "site_id", "message"]
+    }
+
+    fn records(&self) -> Vec<&AuditLog> {
+        self.0.iter().collect()
+    }
+}
+
+impl<'a> Iterator for AuditLogPages<'a> {
+    type Item = Result<Vec<AuditLog>, MistError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let url = match serde_urlencoded::to_string(&self.query) {
+            Ok(qstr) => self.c.url(&format!("{}?{}", self.path, qstr)),
+            Err(e) => {
+                self.done = true;
+                return Some(Err(MistError::Invalid(e.to_string())));
+            }
+        };
+        match self.c.get::<_, AuditLogPage>(url, &()) {
+            Ok(page) => {
+                let limit = self.query.limit.unwrap_or(self.c.page_size());
+                self.fetched += page.results.len() as u64;
+                self.done = page.results.len() < limit as usize
+                    || page.total.is_some_and(|total| self.fetched >= total);
+                self.query.page = Some(self.query.page.unwrap_or(1) + 1);
+                if page.results.is_empty() && self.fetched > 0 {
+                    return None;
+                }
+                Some(Ok(page.results))
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// One page of the audit logs of the current user
+pub fn list_self<'a>(c: &HttpClient, query: &'a AuditLogQuery) -> Result<AuditLogs, MistError> {
+    list(c, paths::AUDIT_LOG.to_string(), query)
+}
+
+/// One page of the audit logs of an org
+pub fn list_org<'a>(
+    c: &HttpClient,
+    org_id: &'a OrgId,
+    query: &'a AuditLogQuery,
+) -> Result<AuditLogs, MistError> {
+    list(c, org_logs_path(org_id), query)
+}
+
+/// Pages of the audit logs of the current user, fetched lazily
+pub fn self_pages<'a>(c: &'a HttpClient, query: &'a AuditLogQuery) -> AuditLogPages<'a> {
+    pages(c, paths::AUDIT_LOG.to_string(), query)
+}
+
+/// Pages of the audit logs of an org, fetched lazily
+pub fn org_pages<'a>(
+    c: &'a HttpClient,
+    org_id: &'a OrgId,
+    query: &'a AuditLogQuery,
+) -> AuditLogPages<'a> {
+    pages(c, org_logs_path(org_id), query)
+}
+
+/// Every audit log of an org matching the query
+pub fn list_org_all<'a>(
+    c: &HttpClient,
+    org_id: &'a OrgId,
+    query: &'a AuditLogQuery,
+) -> Result<AuditLogs, MistError> {
+    let mut logs = Vec::new();
+    for page in org_pages(c, org_id, query) {
+        logs.extend(page?);
+    }
+    Ok(AuditLogs(logs))
+}
+
+/// Every audit log of the current user matching the query
+pub fn list_self_all<'a>(c: &HttpClient, query: &'a AuditLogQuery) -> Result<AuditLogs, MistError> {
+    let mut logs = Vec::new();
+    for page in self_pages(c, query) {
+        logs.extend(page?);
+    }
+    Ok(AuditLogs(logs))
+}
+
+// private functions
+
+fn list<'a>(
+    c: &HttpClient,
+    path: String,
+    query: &'a AuditLogQuery,
+) -> Result<AuditLogs, MistError> {
+    let qstr = serde_urlencoded::to_string(query).map_err(|e| MistError::Invalid(e.to_string()))?;
+    match c.get::<_, AuditLogPage>(c.url(&format!("{}?{}", path, qstr)), &()) {
+        Ok(page) => {
+            debug("list audit logs request succeed");
+            Ok(AuditLogs(page.results))
+        }
+        Err(e) => {
+            warn(&format!("list audit logs request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+fn pages<'a>(c: &'a HttpClient, path: String, query: &'a AuditLogQuery) -> AuditLogPages<'a> {
+    let mut query = query.clone();
+    query.limit = Some(query.limit.unwrap_or_else(|| c.page_size()));
+    query.page = Some(query.page.unwrap_or(1));
+    AuditLogPages {
+        c,
+        path,
+        query,
+        fetched: 0,
+        done: false,
+    }
+}
+
+fn org_logs_path<'a>(org_id: &'a OrgId) -> String {
+    format!("orgs/{}/logs", org_id)
+}
@@ -2,86 +2,285 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::auth::paths;
+use crate::error::MistError;
 use crate::http::HttpClient;
 use crate::logger::{debug, info, warn};
-use serde::Deserialize;
+use crate::output::Render;
+use crate::types::{MspId, OrgId, Role, Scope, SiteId};
+use serde::{Deserialize, Serialize};
 use serde_json::Value::Object;
 use std::collections::HashMap;
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct Whoami {
+    // org tokens have a name and privileges only
+    #[serde(default)]
     pub email: String,
+    #[serde(default)]
     pub first_name: String,
+    #[serde(default)]
     pub last_name: String,
     #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
     pub phone: Option<String>,
     #[serde(default)]
     pub via_sso: Option<String>,
     pub privileges: Vec<Privilege>,
+    #[serde(default)]
     pub tags: Vec<String>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Privilege {
-    pub scope: String,
-    pub org_id: String,
+    pub scope: Scope,
+    // none for msp and orggroup privileges
+    #[serde(default)]
+    pub org_id: Option<OrgId>,
     #[serde(default)]
     pub org_name: Option<String>,
     #[serde(default)]
-    pub msp_id: Option<String>,
+    pub msp_id: Option<MspId>,
     #[serde(default)]
     pub msp_name: Option<String>,
     #[serde(default)]
     pub orggroup_ids: Option<Vec<String>>,
+    #[serde(default)]
     pub name: String,
-    pub role: String,
+    pub role: Role,
     #[serde(default)]
-    pub site_id: Option<String>,
+    pub site_id: Option<SiteId>,
     #[serde(default)]
     pub sitegroup_ids: Option<Vec<String>>,
+    // privileges of org admins and invites name one site group
+    #[serde(default)]
+    pub sitegroup_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct LoginLookup {
+    // IdP to sign in with, absent for password accounts
+    #[serde(default)]
+    pub sso_url: Option<String>,
+}
+
+/// Secret to enroll in an authenticator app
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct TwoFactorToken {
+    pub secret: String,
+    // otpauth:// URI for authenticator apps, filled in by `two_factor_token`
+    #[serde(default)]
+    pub uri: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum LoginMethod {
+    Password,
+    Sso(String),
+}
+
+impl LoginLookup {
+    pub fn method(&self) -> LoginMethod {
+        match &self.sso_url {
+            Some(url) if !url.is_empty() => LoginMethod::Sso(url.clone()),
+            _ => LoginMethod::Password,
+        }
+    }
 }
 
-pub fn login<'a>(c: &HttpClient, u: &'a str, p: &'a str, otp: Option<&'a str>) -> Result<(), ()> {
+impl Render for LoginLookup {
+    type Record = LoginLookup;
+
+    fn default_columns() -> &'static [&'static str] {
+        &["sso_url"]
+    }
+
+    fn records(&self) -> Vec<&LoginLookup> {
+        vec![self]
+    }
+}
+
+impl Render for TwoFactorToken {
+    type Record = TwoFactorToken;
+
+    fn default_columns() -> &'static [&'static str] {
+        &["secret", "uri"]
+    }
+
+    fn records(&self) -> Vec<&TwoFactorToken> {
+        vec![self]
+    }
+}
+
+impl Render for Whoami {
+    type Record = Privilege;
+
+    fn default_columns() -> &'static [&'static str] {
+        &["scope", "role", "name", "org_id", "site_id"]
+    }
+
+    fn records(&self) -> Vec<&Privilege> {
+        self.privileges.iter().collect()
+    }
+}
+
+pub fn login<'a>(
+    c: &HttpClient,
+    u: &'a str,
+    p: &'a str,
+    otp: Option<&'a str>,
+) -> Result<(), MistError> {
     let req = login_request(u, p, otp);
-    match c.post(paths::login(), &req) {
-        Ok(Some(Object(map))) if map.is_empty() => {
+    match c.post(paths::login(c), &req) {
+        Ok(Object(map)) if map.is_empty() => {
             info("Login succeed");
+            if !c.is_logged_in() {
+                warn("login succeeded without a session cookie");
+            }
             Ok(())
         }
-        _ => {
+        Ok(reply) if two_factor_required(&reply) && otp.is_some() => {
+            warn("login failed: the two-factor code incorrect");
+            Err(MistError::auth("two-factor code incorrect"))
+        }
+        Ok(reply) if two_factor_required(&reply) => {
+            info("Login needs a two-factor code");
+            Err(MistError::TwoFactorRequired)
+        }
+        Ok(reply) => {
             warn("login failed: the credentials incorrect");
-            Err(())
+            Err(MistError::Auth {
+                status: None,
+                detail: Some(reply.to_string()),
+            })
+        }
+        Err(MistError::Status { status, detail, .. }) if status.is_client_error() => {
+            warn("login failed: the credentials incorrect");
+            Err(MistError::Auth {
+                status: Some(status),
+                detail,
+            })
+        }
+        Err(e) => {
+            warn(&format!("login failed: {}", e));
+            Err(e)
         }
     }
 }
 
-pub fn logout(c: &HttpClient) -> Result<(), ()> {
-    match c.post::<(), serde_json::Value>(paths::logout(), &()) {
+/// Completes a login answered with `TwoFactorRequired`, using the
+/// session cookie of that first step
+pub fn two_factor<'a>(c: &HttpClient, code: &'a str) -> Result<(), MistError> {
+    let mut req: HashMap<&str, &str> = HashMap::new();
+    req.insert("two_factor", code);
+    match c.post::<_, serde_json::Value>(paths::login_two_factor(c), &req) {
         Ok(_) => {
-            info("Logout succeed");
+            info("Login succeed");
+            Ok(())
+        }
+        Err(MistError::Status { status, detail, .. }) if status.is_client_error() => {
+            warn("login failed: the two-factor code incorrect");
+            Err(MistError::Auth {
+                status: Some(status),
+                detail,
+            })
+        }
+        Err(e) => {
+            warn(&format!("login failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+/// Generates the secret to enroll the current user in two-factor
+/// authentication. Enrollment completes with `verify_two_factor`.
+pub fn two_factor_token(c: &HttpClient) -> Result<TwoFactorToken, MistError> {
+    match c.get::<_, TwoFactorToken>(paths::two_factor_token(c), &()) {
+        Ok(mut token) => {
+            debug("two_factor token request succeed");
+            let email = get_priviledges(c)?.email;
+            token.uri = Some(otpauth_uri(&email, &token.secret));
+            Ok(token)
+        }
+        Err(e) => {
+            warn(&format!("two_factor token request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+/// Enables two-factor authentication with a code of the enrolled app
+pub fn verify_two_factor<'a>(c: &HttpClient, code: &'a str) -> Result<(), MistError> {
+    let mut req: HashMap<&str, &str> = HashMap::new();
+    req.insert("two_factor", code);
+    match c.post::<_, serde_json::Value>(paths::two_factor_verify(c), &req) {
+        Ok(_) => {
+            info("two-factor authentication enabled");
             Ok(())
         }
-        Err(_) => {
-            warn("logout failed");
-            Err(())
+        Err(e) => {
+            warn(&format!("two_factor verify request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+/// Tells whether the account signs in with a password or an SSO IdP.
+/// SSO accounts can not use `login` and need an API token instead.
+pub fn lookup<'a>(c: &HttpClient, email: &'a str) -> Result<LoginLookup, MistError> {
+    let mut req: HashMap<&str, &str> = HashMap::new();
+    req.insert("email", email);
+    // an empty body means a password account
+    match c.post_idempotent::<_, Option<LoginLookup>>(paths::login_lookup(c), &req) {
+        Ok(lookup) => {
+            debug("login lookup request succeed");
+            Ok(lookup.unwrap_or_default())
+        }
+        Err(e) => {
+            warn(&format!("login lookup request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+pub fn logout(c: &HttpClient) -> Result<(), MistError> {
+    match c.post::<(), serde_json::Value>(paths::logout(c), &()) {
+        Ok(_) => {
+            info("Logout succeed");
+            c.clear_session()
+        }
+        Err(e) if e.is_auth() => {
+            // the session was already gone on the server
+            info("Logout succeed");
+            c.clear_session()
+        }
+        Err(e) => {
+            warn(&format!("logout failed: {}", e));
+            Err(e)
         }
     }
 }
 
-pub fn get_priviledges(c: &HttpClient) -> Result<Whoami, ()> {
-    match c.get(paths::get_privileges(), &()) {
-        Ok(Some(whoami)) => {
+pub fn get_priviledges(c: &HttpClient) -> Result<Whoami, MistError> {
+    match c.get(paths::get_privileges(c), &()) {
+        Ok(whoami) => {
             debug("get_privileges request succeed");
             Ok(whoami)
         }
-        _ => {
-            warn("get_privileges request failed");
-            Err(())
+        Err(e) => {
+            warn(&format!("get_privileges request failed: {}", e));
+            Err(e)
         }
     }
 }
 
-/// private functions
+// private functions
+
+// `{"two_factor_required": true, "two_factor_passed": false}`
+fn two_factor_required(reply: &serde_json::Value) -> bool {
+    reply["two_factor_required"].as_bool().unwrap_or(false)
+        && !reply["two_factor_passed"].as_bool().unwrap_or(false)
+}
 
 fn login_request<'a>(n: &'a str, p: &'a str, otp: Option<&'a str>) -> HashMap<String, String> {
     let mut req: HashMap<String, String> = HashMap::new();
@@ -92,3 +291,40 @@ fn login_request<'a>(n: &'a str, p: &'a str, otp: Option<&'a str>) -> HashMap<St
     }
     req
 }
+
+// the label is a path segment, so spaces are %20 rather than `+`
+fn otpauth_uri<'a>(email: &'a str, secret: &'a str) -> String {
+    let encode = |value: &str| -> String {
+        value
+            .bytes()
+            .map(|b| match b {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                    (b as char).to_string()
+                }
+                _ => format!("%{:02X}", b),
+            })
+            .collect()
+    };
+    format!(
+        "otpauth://totp/Mist:{}?secret={}&issuer=Mist",
+        encode(email),
+        encode(secret)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn otpauth_label_is_percent_encoded() {
+        assert_eq!(
+            otpauth_uri("alice@example.com", "JBSWY3DPEHPK3PXP"),
+            "otpauth://totp/Mist:alice%40example.com?secret=JBSWY3DPEHPK3PXP&issuer=Mist"
+        );
+        assert_eq!(
+            otpauth_uri("a+b c/d?e&f#g", "ABC"),
+            "otpauth://totp/Mist:a%2Bb%20c%2Fd%3Fe%26f%23g?secret=ABC&issuer=Mist"
+        );
+    }
+}
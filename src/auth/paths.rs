@@ -1,27 +1,67 @@
 // Copyright 2020 Eishun Kondoh
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::consts::MIST_API_BASE;
+use crate::http::HttpClient;
+use crate::types::OrgId;
 
 /// ## Auth
 /// ### Login/Logout/Lookup
 pub const LOGIN: &'static str = "login";
 pub const LOGIN_LOOKUP: &'static str = "login/lookup";
 pub const LOGOUT: &'static str = "logout";
+pub const LOGIN_TWO_FACTOR: &'static str = "login/two_factor";
 
 /// ### Privileges(Self)
 pub const PRIV_WHOAMI: &'static str = "self";
+/// ### Two-factor enrollment
+pub const TWO_FACTOR_TOKEN: &'static str = "self/two_factor/token";
+pub const TWO_FACTOR_VERIFY: &'static str = "self/two_factor/verify";
 /// ### Audit Logs
 pub const AUDIT_LOG: &'static str = "self/logs";
 
-pub fn login() -> String {
-    format!("{}/{}", MIST_API_BASE, LOGIN)
+pub fn login(c: &HttpClient) -> String {
+    c.url(LOGIN)
 }
 
-pub fn logout() -> String {
-    format!("{}/{}", MIST_API_BASE, LOGOUT)
+pub fn login_lookup(c: &HttpClient) -> String {
+    c.url(LOGIN_LOOKUP)
 }
 
-pub fn get_privileges() -> String {
-    format!("{}/{}", MIST_API_BASE, PRIV_WHOAMI)
+pub fn login_two_factor(c: &HttpClient) -> String {
+    c.url(LOGIN_TWO_FACTOR)
+}
+
+pub fn two_factor_token(c: &HttpClient) -> String {
+    c.url(TWO_FACTOR_TOKEN)
+}
+
+pub fn two_factor_verify(c: &HttpClient) -> String {
+    c.url(TWO_FACTOR_VERIFY)
+}
+
+pub fn logout(c: &HttpClient) -> String {
+    c.url(LOGOUT)
+}
+
+pub fn get_privileges(c: &HttpClient) -> String {
+    c.url(PRIV_WHOAMI)
+}
+
+/// ### API Tokens
+pub const API_TOKENS: &'static str = "self/apitokens";
+
+pub fn api_tokens(c: &HttpClient) -> String {
+    c.url(API_TOKENS)
+}
+
+pub fn api_token<'a>(c: &HttpClient, token_id: &'a str) -> String {
+    c.url(&format!("{}/{}", API_TOKENS, token_id))
+}
+
+pub fn org_api_tokens<'a>(c: &HttpClient, org_id: &'a OrgId) -> String {
+    c.url(&format!("orgs/{}/apitokens", org_id))
+}
+
+pub fn org_api_token<'a>(c: &HttpClient, org_id: &'a OrgId, token_id: &'a str) -> String {
+    c.url(&format!("orgs/{}/apitokens/{}", org_id, token_id))
 }
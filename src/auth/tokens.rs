@@ -0,0 +1,297 @@
+// Copyright 2020 Eishun Kondoh
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::auth::{login, paths};
+use crate::error::MistError;
+use crate::http::HttpClient;
+use crate::logger::{debug, info, warn};
+use crate::output::Render;
+use crate::types::{OrgId, Role, Scope, SiteId};
+use serde::{Deserialize, Serialize};
+
+///
+/// ref: https://api.mist.com/api/v1/docs/Self#api-token
+/// ref: https://api.mist.com/api/v1/docs/Org#api-token
+///
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ApiTokens(Vec<ApiToken>);
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct ApiToken {
+    pub id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    // the whole key is only returned on creation, listings mask it
+    pub key: String,
+    #[serde(default)]
+    pub created_by: Option<String>,
+    #[serde(default)]
+    pub created_time: Option<f64>,
+    #[serde(default)]
+    pub last_used: Option<f64>,
+    // org tokens only
+    #[serde(default)]
+    pub privileges: Vec<TokenPrivilege>,
+    #[serde(default)]
+    pub src_ips: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TokenPrivilege {
+    // org, site or sitegroup
+    pub scope: Scope,
+    pub role: Role,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub site_id: Option<SiteId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sitegroup_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct ApiTokenParams {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    // required for org tokens, ignored for user tokens
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub privileges: Vec<TokenPrivilege>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub src_ips: Option<Vec<String>>,
+}
+
+impl ApiToken {
+    /// Whether the key of a listing, where the middle of the key is
+    /// masked with `*` or `.`, belongs to `key`
+    pub fn matches_key<'a>(&self, key: &'a str) -> bool {
+        let masked = |ch: char| ch == '*' || ch == '.';
+        if !self.key.contains(masked) {
+            return self.key == key;
+        }
+        let head = self.key.split(masked).next().unwrap_or("");
+        let tail = self.key.rsplit(masked).next().unwrap_or("");
+        !(head.is_empty() && tail.is_empty())
+            && key.len() >= head.len() + tail.len()
+            && key.starts_with(head)
+            && key.ends_with(tail)
+    }
+
+    fn params(&self) -> ApiTokenParams {
+        ApiTokenParams {
+            name: self.name.clone(),
+            privileges: self.privileges.clone(),
+            src_ips: self.src_ips.clone(),
+        }
+    }
+}
+
+impl From<Vec<ApiToken>> for ApiTokens {
+    fn from(v: Vec<ApiToken>) -> Self {
+        ApiTokens(v)
+    }
+}
+
+impl Render for ApiTokens {
+    type Record = ApiToken;
+
+    fn default_columns() -> &'static [&'static str] {
+        &["id", "name", "key", "created_time", "last_used"]
+    }
+
+    fn records(&self) -> Vec<&ApiToken> {
+        self.0.iter().collect()
+    }
+}
+
+impl Render for ApiToken {
+    type Record = ApiToken;
+
+    fn default_columns() -> &'static [&'static str] {
+        &["id", "name", "key"]
+    }
+
+    fn records(&self) -> Vec<&ApiToken> {
+        vec![self]
+    }
+}
+
+pub fn list(c: &HttpClient) -> Result<ApiTokens, MistError> {
+    match c.get(paths::api_tokens(c), &()) {
+        Ok(tokens) => {
+            debug("list api tokens request succeed");
+            Ok(tokens)
+        }
+        Err(e) => {
+            warn(&format!("list api tokens request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+pub fn create<'a>(c: &HttpClient, params: &'a ApiTokenParams) -> Result<ApiToken, MistError> {
+    match c.post(paths::api_tokens(c), params) {
+        Ok(token) => {
+            debug("create api token request succeed");
+            Ok(token)
+        }
+        Err(e) => {
+            warn(&format!("create api token request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+pub fn delete<'a>(c: &HttpClient, token_id: &'a str) -> Result<(), MistError> {
+    match c.delete(paths::api_token(c, token_id), &()) {
+        Ok(_) => {
+            debug("delete api token request succeed");
+            Ok(())
+        }
+        Err(e) => {
+            warn(&format!("delete api token request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+pub fn list_org<'a>(c: &HttpClient, org_id: &'a OrgId) -> Result<ApiTokens, MistError> {
+    match c.get(paths::org_api_tokens(c, org_id), &()) {
+        Ok(tokens) => {
+            debug("list org api tokens request succeed");
+            Ok(tokens)
+        }
+        Err(e) => {
+            warn(&format!("list org api tokens request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+pub fn create_org<'a>(
+    c: &HttpClient,
+    org_id: &'a OrgId,
+    params: &'a ApiTokenParams,
+) -> Result<ApiToken, MistError> {
+    if params.privileges.is_empty() {
+        return Err(MistError::Invalid(
+            "org api tokens need at least one privilege".into(),
+        ));
+    }
+    match c.post(paths::org_api_tokens(c, org_id), params) {
+        Ok(token) => {
+            debug("create org api token request succeed");
+            Ok(token)
+        }
+        Err(e) => {
+            warn(&format!("create org api token request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+pub fn delete_org<'a>(
+    c: &HttpClient,
+    org_id: &'a OrgId,
+    token_id: &'a str,
+) -> Result<(), MistError> {
+    match c.delete(paths::org_api_token(c, org_id, token_id), &()) {
+        Ok(_) => {
+            debug("delete org api token request succeed");
+            Ok(())
+        }
+        Err(e) => {
+            warn(&format!("delete org api token request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+/// Looks up the listing entry of the token the client authenticates with
+pub fn find_current<'a>(tokens: &'a ApiTokens, key: &'a str) -> Result<&'a ApiToken, MistError> {
+    let found: Vec<&ApiToken> = tokens.0.iter().filter(|t| t.matches_key(key)).collect();
+    match found.as_slice() {
+        [token] => Ok(token),
+        [] => Err(MistError::Invalid(
+            "the current token is not in the listing, give its id".into(),
+        )),
+        _ => Err(MistError::Invalid(
+            "several tokens match the current key, give its id".into(),
+        )),
+    }
+}
+
+/// Replaces a token with a new one having the same name and privileges.
+///
+/// The new token is created, checked with `get_priviledges` and handed
+/// to `persist`; the old one is deleted only once all of that succeeded,
+/// otherwise the new token is deleted and the old one is left in place.
+/// `org_id` selects an org token, `None` a user token.
+pub fn rotate<'a, F>(
+    c: &HttpClient,
+    org_id: Option<&'a OrgId>,
+    old_id: &'a str,
+    persist: F,
+) -> Result<ApiToken, MistError>
+where
+    F: FnOnce(&ApiToken) -> Result<(), MistError>,
+{
+    let old = match org_id {
+        Some(org_id) => list_org(c, org_id)?,
+        None => list(c)?,
+    }
+    .0
+    .into_iter()
+    .find(|t| t.id == old_id)
+    .ok_or_else(|| MistError::Invalid(format!("api token {} not found", old_id)))?;
+    let new = match org_id {
+        Some(org_id) => create_org(c, org_id, &old.params())?,
+        None => create(c, &old.params())?,
+    };
+    if let Err(e) = verify(c, org_id, &new).and_then(|_| persist(&new)) {
+        warn(&format!(
+            "rotation aborted, deleting api token {}: {}",
+            new.id, e
+        ));
+        let _ = match org_id {
+            Some(org_id) => delete_org(c, org_id, &new.id),
+            None => delete(c, &new.id),
+        };
+        return Err(e);
+    }
+    // the old token is removed with the new one, proving it is usable
+    let nc = c.with_token(&new.key)?;
+    let deleted = match org_id {
+        Some(org_id) => delete_org(&nc, org_id, old_id),
+        None => delete(&nc, old_id),
+    };
+    if let Err(e) = deleted {
+        warn(&format!("api token {} replaced but not deleted", old_id));
+        return Err(e);
+    }
+    info(&format!("api token {} rotated to {}", old_id, new.id));
+    Ok(new)
+}
+
+// private functions
+
+fn verify<'a>(
+    c: &HttpClient,
+    org_id: Option<&'a OrgId>,
+    token: &'a ApiToken,
+) -> Result<(), MistError> {
+    let nc = c.with_token(&token.key)?;
+    let whoami = login::get_priviledges(&nc)?;
+    match org_id {
+        Some(org_id)
+            if !whoami
+                .privileges
+                .iter()
+                .any(|p| p.org_id.as_ref() == Some(org_id)) =>
+        {
+            Err(MistError::Auth {
+                status: None,
+                detail: Some(format!("new api token has no privilege on org {}", org_id)),
+            })
+        }
+        _ => Ok(()),
+    }
+}
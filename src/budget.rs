@@ -0,0 +1,339 @@
+// Copyright 2020 Eishun Kondoh
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::error::MistError;
+use crate::logger::{debug, warn};
+use crate::output::Render;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// API calls allowed per token and hour
+pub const HOURLY_QUOTA: u32 = 5000;
+
+const WINDOW_MS: u64 = 3600 * 1000;
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+const STALE_LOCK: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone)]
+pub struct BudgetConfig {
+    // requests allowed in a sliding hour
+    pub quota: u32,
+    // fraction of the quota after which requests are paced to the
+    // sustainable rate (quota per hour), None only blocks at the quota
+    pub throttle_at: Option<f64>,
+    // state shared by every process using the same token
+    pub state_file: Option<PathBuf>,
+}
+
+/// Counts the requests sent with a token over the last hour.
+///
+/// Without a state file the count covers this process only. With one,
+/// every process records its requests in the file, under a lock file,
+/// so that concurrent jobs sharing a token see the same budget.
+#[derive(Debug)]
+pub struct Budget {
+    key: String,
+    config: BudgetConfig,
+    sent: Mutex<Vec<u64>>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct BudgetStatus {
+    pub quota: u32,
+    pub used: u32,
+    pub remaining: u32,
+    pub throttle_at: Option<u32>,
+    pub state_file: Option<String>,
+}
+
+impl Default for BudgetConfig {
+    fn default() -> Self {
+        BudgetConfig {
+            quota: HOURLY_QUOTA,
+            throttle_at: None,
+            state_file: None,
+        }
+    }
+}
+
+impl Render for BudgetStatus {
+    type Record = BudgetStatus;
+
+    fn default_columns() -> &'static [&'static str] {
+        &["quota", "used", "remaining", "throttle_at"]
+    }
+
+    fn records(&self) -> Vec<&BudgetStatus> {
+        vec![self]
+    }
+}
+
+impl Budget {
+    /// `token` only identifies the budget, it is stored as a hash
+    pub fn new<'a>(token: Option<&'a str>, config: BudgetConfig) -> Self {
+        let key = match token {
+            Some(token) => format!("{:016x}", fnv1a(token.as_bytes())),
+            None => "session".to_string(),
+        };
+        Budget {
+            key,
+            config,
+            sent: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn used(&self) -> Result<u32, MistError> {
+        let now = now_ms();
+        self.with_window(|sent| {
+            prune(sent, now);
+            (sent.len() as u32, false)
+        })
+    }
+
+    pub fn remaining(&self) -> Result<u32, MistError> {
+        Ok(self.config.quota.saturating_sub(self.used()?))
+    }
+
+    pub fn status(&self) -> Result<BudgetStatus, MistError> {
+        let used = self.used()?;
+        Ok(BudgetStatus {
+            quota: self.config.quota,
+            used,
+            remaining: self.config.quota.saturating_sub(used),
+            throttle_at: self.throttle_threshold(),
+            state_file: self
+                .config
+                .state_file
+                .as_ref()
+                .map(|path| path.display().to_string()),
+        })
+    }
+
+    /// Records one request, first waiting while the quota is used up or,
+    /// past the throttle threshold, until the pacing interval elapsed.
+    pub fn acquire(&self) -> Result<(), MistError> {
+        loop {
+            let now = now_ms();
+            let wait = self.with_window(|sent| {
+                prune(sent, now);
+                match self.wait_ms(sent, now) {
+                    0 => {
+                        sent.push(now);
+                        (0, true)
+                    }
+                    wait => (wait, false),
+                }
+            })?;
+            if wait == 0 {
+                return Ok(());
+            }
+            debug(&format!("API budget: waiting {} ms", wait));
+            thread::sleep(Duration::from_millis(wait));
+        }
+    }
+
+    fn throttle_threshold(&self) -> Option<u32> {
+        self.config
+            .throttle_at
+            .map(|fraction| (f64::from(self.config.quota) * fraction.clamp(0.0, 1.0)) as u32)
+    }
+
+    fn wait_ms(&self, sent: &[u64], now: u64) -> u64 {
+        // a quota of 0 would block forever, it allows one request instead
+        let quota = self.config.quota.max(1);
+        let used = sent.len() as u32;
+        if used >= quota {
+            // the oldest request has to leave the window first
+            let oldest = sent.len() - quota as usize;
+            return (sent[oldest] + WINDOW_MS).saturating_sub(now).max(1);
+        }
+        match (self.throttle_threshold(), sent.last()) {
+            (Some(threshold), Some(last)) if used >= threshold => {
+                let interval = WINDOW_MS / u64::from(quota);
+                (last + interval).saturating_sub(now)
+            }
+            _ => 0,
+        }
+    }
+
+    /// Runs `f` on the request timestamps, from the state file when one
+    /// is configured; `f` returns whether the timestamps must be saved.
+    fn with_window<T, F>(&self, f: F) -> Result<T, MistError>
+    where
+        F: FnOnce(&mut Vec<u64>) -> (T, bool),
+    {
+        let mut sent = self.sent.lock().unwrap_or_else(|e| e.into_inner());
+        let path = match &self.config.state_file {
+            None => return Ok(f(&mut sent).0),
+            Some(path) => path,
+        };
+        let _lock = FileLock::acquire(path)?;
+        let mut state = load_state(path);
+        let window = state.entry(self.key.clone()).or_default();
+        let (value, dirty) = f(window);
+        if dirty {
+            let now = now_ms();
+            state.retain(|_, sent| {
+                prune(sent, now);
+                !sent.is_empty()
+            });
+            save_state(path, &state)?;
+        }
+        Ok(value)
+    }
+}
+
+// private functions
+
+struct FileLock(PathBuf);
+
+impl FileLock {
+    fn acquire(path: &Path) -> Result<FileLock, MistError> {
+        let lock = path.with_extension("lock");
+        if let Some(dir) = lock.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let started = SystemTime::now();
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&lock) {
+                Ok(_) => return Ok(FileLock(lock)),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    if is_stale(&lock) {
+                        warn(&format!("removing stale lock {}", lock.display()));
+                        let _ = fs::remove_file(&lock);
+                        continue;
+                    }
+                    if started.elapsed().unwrap_or_default() > LOCK_TIMEOUT {
+                        return Err(MistError::Config(format!(
+                            "timed out waiting for {}",
+                            lock.display()
+                        )));
+                    }
+                    thread::sleep(Duration::from_millis(10));
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+fn is_stale(lock: &Path) -> bool {
+    fs::metadata(lock)
+        .and_then(|m| m.modified())
+        .map(|modified| modified.elapsed().unwrap_or_default() > STALE_LOCK)
+        .unwrap_or(false)
+}
+
+fn load_state(path: &Path) -> HashMap<String, Vec<u64>> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_state(path: &Path, state: &HashMap<String, Vec<u64>>) -> Result<(), MistError> {
+    // write then rename, so readers never see a partial file
+    let tmp = path.with_extension("tmp");
+    let content = serde_json::to_string(state).map_err(std::io::Error::from)?;
+    fs::write(&tmp, content)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+fn prune(sent: &mut Vec<u64>, now: u64) {
+    let start = now.saturating_sub(WINDOW_MS);
+    sent.retain(|at| *at > start);
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+// FNV-1a, stable across builds unlike the std hasher
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ u64::from(*b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 10 * WINDOW_MS;
+
+    fn budget(quota: u32, throttle_at: Option<f64>) -> Budget {
+        let config = BudgetConfig {
+            quota,
+            throttle_at,
+            state_file: None,
+        };
+        Budget::new(Some("token"), config)
+    }
+
+    #[test]
+    fn quota_blocks_until_the_oldest_request_leaves_the_window() {
+        let b = budget(3, None);
+        assert_eq!(b.wait_ms(&[NOW - 3000, NOW - 2000], NOW), 0);
+        let sent = [NOW - 3000, NOW - 2000, NOW - 1000];
+        assert_eq!(b.wait_ms(&sent, NOW), WINDOW_MS - 3000);
+        // requests recorded by other processes may exceed the quota
+        let sent = [NOW - 4000, NOW - 3000, NOW - 2000, NOW - 1000];
+        assert_eq!(b.wait_ms(&sent, NOW), WINDOW_MS - 3000);
+        // always waits a little once the quota is used
+        assert_eq!(b.wait_ms(&sent, NOW + WINDOW_MS), 1);
+    }
+
+    #[test]
+    fn zero_quota_allows_one_request() {
+        let b = budget(0, Some(0.5));
+        assert_eq!(b.wait_ms(&[], NOW), 0);
+        assert_eq!(b.wait_ms(&[NOW - 1000], NOW), WINDOW_MS - 1000);
+    }
+
+    #[test]
+    fn throttle_paces_requests_past_the_threshold() {
+        // one request every 1000 ms once 2 of 3600 are used
+        let b = budget(3600, Some(2.0 / 3600.0));
+        assert_eq!(b.wait_ms(&[NOW - 100], NOW), 0);
+        assert_eq!(b.wait_ms(&[NOW - 500, NOW - 100], NOW), 900);
+        assert_eq!(b.wait_ms(&[NOW - 1500, NOW - 1000], NOW), 0);
+        // without throttling only the quota blocks
+        let b = budget(3600, None);
+        assert_eq!(b.wait_ms(&[NOW - 500, NOW - 100], NOW), 0);
+    }
+
+    #[test]
+    fn throttle_threshold_is_clamped_to_the_quota() {
+        assert_eq!(budget(5000, Some(0.8)).throttle_threshold(), Some(4000));
+        assert_eq!(budget(5000, Some(1.5)).throttle_threshold(), Some(5000));
+        assert_eq!(budget(5000, Some(-1.0)).throttle_threshold(), Some(0));
+        assert_eq!(budget(5000, None).throttle_threshold(), None);
+    }
+
+    #[test]
+    fn acquire_records_requests_and_prunes_the_window() {
+        let b = budget(10, None);
+        b.acquire().unwrap();
+        b.acquire().unwrap();
+        assert_eq!(b.used().unwrap(), 2);
+        assert_eq!(b.remaining().unwrap(), 8);
+        let mut sent = vec![NOW - WINDOW_MS, NOW - WINDOW_MS + 1, NOW];
+        prune(&mut sent, NOW);
+        assert_eq!(sent, vec![NOW - WINDOW_MS + 1, NOW]);
+    }
+}
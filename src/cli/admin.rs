@@ -0,0 +1,157 @@
+// Copyright 2020 Eishun Kondoh
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::auth::tokens::TokenPrivilege;
+use crate::cli::token::parse_privilege;
+use crate::cli::Context;
+use crate::error::MistError;
+use crate::org::admins::{self, InviteParams};
+use crate::org::roster;
+use clap::Clap;
+use std::path::PathBuf;
+
+#[derive(Clap, Debug)]
+pub enum AdminCmd {
+    /// List the admins of an org
+    List {
+        #[clap(long)]
+        org: Option<String>,
+    },
+    /// Get the privileges of an admin
+    Get {
+        #[clap(long)]
+        org: Option<String>,
+        admin_id: String,
+    },
+    /// Replace the privileges of an admin on the org
+    Update {
+        #[clap(long)]
+        org: Option<String>,
+        admin_id: String,
+        /// scope:role[:id], e.g. org:admin, site:write:<site_id>,
+        /// sitegroup:read:<sitegroup_id>
+        #[clap(long = "privilege", required = true, parse(try_from_str = parse_privilege))]
+        privileges: Vec<TokenPrivilege>,
+    },
+    /// Revoke every privilege of an admin on the org
+    Revoke {
+        #[clap(long)]
+        org: Option<String>,
+        admin_id: String,
+    },
+    /// Invite the people of a YAML roster who are not admins yet and
+    /// revoke the admins missing from it
+    Sync {
+        #[clap(long)]
+        org: Option<String>,
+        file: PathBuf,
+        /// only list the invites and revocations
+        #[clap(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Clap, Debug)]
+pub enum InviteCmd {
+    /// List the pending invites of an org
+    List {
+        #[clap(long)]
+        org: Option<String>,
+    },
+    /// Invite a person by email to become an admin
+    Create {
+        #[clap(long)]
+        org: Option<String>,
+        email: String,
+        #[clap(long)]
+        first_name: String,
+        #[clap(long)]
+        last_name: String,
+        /// hours the invite is valid for
+        #[clap(long)]
+        hours: Option<u32>,
+        /// scope:role[:id], e.g. org:admin, site:write:<site_id>,
+        /// sitegroup:read:<sitegroup_id>
+        #[clap(long = "privilege", required = true, parse(try_from_str = parse_privilege))]
+        privileges: Vec<TokenPrivilege>,
+    },
+    /// Send the email of a pending invite again
+    Resend {
+        #[clap(long)]
+        org: Option<String>,
+        invite_id: String,
+    },
+    /// Delete a pending invite
+    Delete {
+        #[clap(long)]
+        org: Option<String>,
+        invite_id: String,
+    },
+}
+
+pub fn run_admin(ctx: &Context, cmd: &AdminCmd) -> Result<(), MistError> {
+    let c = &ctx.client;
+    match cmd {
+        AdminCmd::List { org } => ctx.print(&admins::list(c, &ctx.org_id(org)?)?),
+        AdminCmd::Get { org, admin_id } => ctx.print(&admins::get(c, &ctx.org_id(org)?, admin_id)?),
+        AdminCmd::Update {
+            org,
+            admin_id,
+            privileges,
+        } => ctx.print(&admins::update(
+            c,
+            &ctx.writable_org_id(org)?,
+            admin_id,
+            privileges,
+        )?),
+        AdminCmd::Revoke { org, admin_id } => {
+            admins::revoke(c, &ctx.writable_org_id(org)?, admin_id)
+        }
+        AdminCmd::Sync { org, file, dry_run } => {
+            let org_id = if *dry_run {
+                ctx.org_id(org)?
+            } else {
+                ctx.writable_org_id(org)?
+            };
+            let roster = roster::read_roster(file)?;
+            let changes = roster::sync(c, &org_id, &roster, *dry_run, |site| {
+                ctx.org_site_id(&org_id, &Some(site.to_string()))
+            })?;
+            ctx.print(&changes)?;
+            match changes.failed() {
+                0 => Ok(()),
+                n => Err(MistError::Invalid(format!("{} roster changes failed", n))),
+            }
+        }
+    }
+}
+
+pub fn run_invite(ctx: &Context, cmd: &InviteCmd) -> Result<(), MistError> {
+    let c = &ctx.client;
+    match cmd {
+        InviteCmd::List { org } => ctx.print(&admins::list_invites(c, &ctx.org_id(org)?)?),
+        InviteCmd::Create {
+            org,
+            email,
+            first_name,
+            last_name,
+            hours,
+            privileges,
+        } => {
+            let params = InviteParams {
+                email: email.clone(),
+                first_name: first_name.clone(),
+                last_name: last_name.clone(),
+                hours: *hours,
+                privileges: privileges.clone(),
+            };
+            ctx.print(&admins::invite(c, &ctx.writable_org_id(org)?, &params)?)
+        }
+        InviteCmd::Resend { org, invite_id } => {
+            admins::resend_invite(c, &ctx.writable_org_id(org)?, invite_id)
+        }
+        InviteCmd::Delete { org, invite_id } => {
+            admins::delete_invite(c, &ctx.writable_org_id(org)?, invite_id)
+        }
+    }
+}
@@ -0,0 +1,193 @@
+// Copyright 2020 Eishun Kondoh
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::audit::audit::{self, AuditLogQuery, AuditLogs};
+use crate::cli::Context;
+use crate::error::MistError;
+use crate::types::SiteId;
+use clap::Clap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Clap, Debug)]
+pub enum AuditCmd {
+    /// Audit logs of the current user
+    #[clap(name = "self")]
+    Own {
+        #[clap(flatten)]
+        filter: LogFilter,
+    },
+    /// Audit logs of an org
+    Org {
+        #[clap(long)]
+        org: Option<String>,
+        #[clap(flatten)]
+        filter: LogFilter,
+    },
+}
+
+#[derive(Clap, Debug)]
+pub struct LogFilter {
+    /// epoch seconds, YYYY-MM-DD (UTC) or a duration ago like 30m, 12h, 7d, 2w
+    #[clap(long, parse(try_from_str = parse_time))]
+    since: Option<u64>,
+    /// same formats as --since
+    #[clap(long, parse(try_from_str = parse_time))]
+    until: Option<u64>,
+    /// changes made to this site, by ID or name
+    #[clap(long)]
+    site: Option<String>,
+    /// changes made by this admin
+    #[clap(long)]
+    admin: Option<String>,
+    /// logs whose message contains this text
+    #[clap(long)]
+    message: Option<String>,
+}
+
+impl LogFilter {
+    fn query(&self, site_id: Option<SiteId>) -> AuditLogQuery {
+        AuditLogQuery {
+            start: self.since,
+            end: self.until,
+            site_id,
+            admin_name: self.admin.clone(),
+            message: self.message.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+pub fn run(ctx: &Context, cmd: &AuditCmd) -> Result<(), MistError> {
+    let c = &ctx.client;
+    match cmd {
+        AuditCmd::Own { filter } => {
+            let site_id = match &filter.site {
+                Some(_) => Some(ctx.site_id(&filter.site)?),
+                None => None,
+            };
+            let query = filter.query(site_id);
+            ctx.print_pages::<AuditLogs, _>(audit::self_pages(c, &query))
+        }
+        AuditCmd::Org { org, filter } => {
+            let org_id = ctx.org_id(org)?;
+            let site_id = match &filter.site {
+                Some(_) => Some(ctx.org_site_id(&org_id, &filter.site)?),
+                None => None,
+            };
+            let query = filter.query(site_id);
+            ctx.print_pages::<AuditLogs, _>(audit::org_pages(c, &org_id, &query))
+        }
+    }
+}
+
+// private functions
+
+fn parse_time(s: &str) -> Result<u64, MistError> {
+    let invalid = || MistError::Invalid(format!("invalid time: {}", s));
+    if let Ok(epoch) = s.parse::<u64>() {
+        return Ok(epoch);
+    }
+    if s.len() == 10 && s.as_bytes()[4] == b'-' && s.as_bytes()[7] == b'-' {
+        let y = s[0..4].parse::<i64>().map_err(|_| invalid())?;
+        let m = s[5..7].parse::<i64>().map_err(|_| invalid())?;
+        let d = s[8..10].parse::<i64>().map_err(|_| invalid())?;
+        if !(1..=12).contains(&m) || !(1..=31).contains(&d) || y < 1970 {
+            return Err(invalid());
+        }
+        return Ok(days_from_civil(y, m, d) as u64 * 86400);
+    }
+    let (n, unit) = match s.char_indices().last() {
+        Some((i, _)) => s.split_at(i),
+        None => return Err(invalid()),
+    };
+    let n = n.parse::<u64>().map_err(|_| invalid())?;
+    let unit = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        "w" => 604_800,
+        _ => return Err(invalid()),
+    };
+    let secs = n.checked_mul(unit).ok_or_else(invalid)?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    Ok(now.saturating_sub(secs))
+}
+
+// days since 1970-01-01 of a proleptic Gregorian date
+fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (m + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ago(secs: u64) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            - secs
+    }
+
+    #[test]
+    fn parse_time_epoch_and_date() {
+        assert_eq!(parse_time("1600000000").unwrap(), 1_600_000_000);
+        // a bare number is epoch seconds, not a duration
+        assert_eq!(parse_time("7").unwrap(), 7);
+        assert_eq!(parse_time("1970-01-01").unwrap(), 0);
+        assert_eq!(parse_time("2020-09-13").unwrap(), 1_599_955_200);
+        assert_eq!(parse_time("2024-02-29").unwrap(), 1_709_164_800);
+    }
+
+    #[test]
+    fn parse_time_durations() {
+        for (s, secs) in [
+            ("30s", 30),
+            ("15m", 900),
+            ("12h", 43_200),
+            ("7d", 604_800),
+            ("2w", 1_209_600),
+        ] {
+            let t = parse_time(s).unwrap();
+            assert!(ago(secs) - 5 <= t && t <= ago(secs), "{}", s);
+        }
+    }
+
+    #[test]
+    fn parse_time_rejects_malformed() {
+        for s in [
+            "",
+            "d",
+            "-7d",
+            "7y",
+            "7 d",
+            "5日",
+            "日",
+            "2020-13-01",
+            "2020-09-32",
+            "1969-12-31",
+            "2020-9-13",
+            "yesterday",
+        ] {
+            assert!(parse_time(s).is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn parse_time_rejects_overflow() {
+        assert!(parse_time("99999999999999999w").is_err());
+        assert!(parse_time("18446744073709551615m").is_err());
+        assert!(parse_time("18446744073709551616s").is_err());
+    }
+}
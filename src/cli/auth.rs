@@ -0,0 +1,89 @@
+// Copyright 2020 Eishun Kondoh
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::auth::login::{self, LoginMethod};
+use crate::cli::Context;
+use crate::error::MistError;
+use clap::Clap;
+use std::env;
+use std::io::{self, IsTerminal};
+use std::time::{Duration, UNIX_EPOCH};
+
+#[derive(Clap, Debug)]
+pub struct LoginCmd {
+    /// defaults to the email of the profile
+    #[clap(long)]
+    email: Option<String>,
+    /// two-factor code, prompted for when required and not given
+    #[clap(long)]
+    otp: Option<String>,
+}
+
+pub fn login(ctx: &Context, cmd: &LoginCmd) -> Result<(), MistError> {
+    let email = cmd
+        .email
+        .clone()
+        .or_else(|| ctx.profile.email.clone())
+        .ok_or_else(|| MistError::Config("email is required: use --email or the profile".into()))?;
+    if let LoginMethod::Sso(url) = login::lookup(&ctx.client, &email)?.method() {
+        return Err(MistError::Auth {
+            status: None,
+            detail: Some(format!(
+                "{} signs in with SSO ({}), password login is not possible: \
+                 create an API token in the portal and set it in the profile",
+                email, url
+            )),
+        });
+    }
+    let password = match env::var("MIST_PASSWORD") {
+        Ok(password) => password,
+        Err(_) => rpassword::read_password_from_tty(Some("Password: "))?,
+    };
+    match login::login(&ctx.client, &email, &password, cmd.otp.as_deref()) {
+        Err(e) if e.is_two_factor_required() && cmd.otp.is_none() => {
+            if !io::stdin().is_terminal() {
+                return Err(MistError::auth("two-factor code required: use --otp"));
+            }
+            let code = rpassword::read_password_from_tty(Some("Two-factor code: "))?;
+            login::two_factor(&ctx.client, code.trim())?;
+        }
+        result => result?,
+    }
+    if let Some(expires) = ctx.client.session_expires_at() {
+        let until = UNIX_EPOCH + Duration::from_secs(expires);
+        println!("logged in until {}", httpdate::fmt_http_date(until));
+    }
+    Ok(())
+}
+
+#[derive(Clap, Debug)]
+pub struct LookupCmd {
+    email: String,
+}
+
+pub fn lookup(ctx: &Context, cmd: &LookupCmd) -> Result<(), MistError> {
+    ctx.print(&login::lookup(&ctx.client, &cmd.email)?)
+}
+
+#[derive(Clap, Debug)]
+pub enum TwoFactorCmd {
+    /// Generate the secret to add to an authenticator app
+    Token,
+    /// Enable two-factor authentication with a code of the app
+    Verify { code: String },
+}
+
+pub fn two_factor(ctx: &Context, cmd: &TwoFactorCmd) -> Result<(), MistError> {
+    match cmd {
+        TwoFactorCmd::Token => ctx.print(&login::two_factor_token(&ctx.client)?),
+        TwoFactorCmd::Verify { code } => login::verify_two_factor(&ctx.client, code),
+    }
+}
+
+pub fn logout(ctx: &Context) -> Result<(), MistError> {
+    login::logout(&ctx.client)
+}
+
+pub fn whoami(ctx: &Context) -> Result<(), MistError> {
+    ctx.print(&login::get_priviledges(&ctx.client)?)
+}
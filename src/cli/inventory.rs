@@ -0,0 +1,354 @@
+// Copyright 2020 Eishun Kondoh
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::cli::Context;
+use crate::error::MistError;
+use crate::inventory::bulk;
+use crate::inventory::inventory::{self, CountBy, Inventories, InventoryQuery, ReplaceInventory};
+use crate::inventory::reconcile::{self, Reconciliation};
+use crate::output::{self, OutputFormat, Render};
+use crate::types::{DeviceType, MacAddr};
+use clap::Clap;
+use std::fs::File;
+use std::io;
+use std::path::PathBuf;
+
+#[derive(Clap, Debug)]
+pub enum InventoryCmd {
+    /// List the inventory of an org
+    List {
+        #[clap(long)]
+        org: Option<String>,
+        #[clap(flatten)]
+        filter: ListFilter,
+    },
+    /// Claim devices with their claim codes
+    Claim {
+        #[clap(long)]
+        org: Option<String>,
+        #[clap(required = true)]
+        codes: Vec<String>,
+    },
+    /// Claim the devices of a CSV file with the columns claim_code, site,
+    /// name and deviceprofile, then assign them to their site and name them
+    ClaimCsv {
+        #[clap(long)]
+        org: Option<String>,
+        file: PathBuf,
+        /// write the result of every row to this CSV file
+        #[clap(long)]
+        report: Option<PathBuf>,
+        /// claim codes per claim request
+        #[clap(long, default_value = "100")]
+        chunk_size: usize,
+    },
+    /// Delete devices from the inventory
+    Delete {
+        #[clap(long)]
+        org: Option<String>,
+        /// comma separated serial numbers
+        #[clap(long, use_delimiter = true)]
+        serials: Vec<String>,
+        /// comma separated MAC addresses
+        #[clap(long, use_delimiter = true)]
+        macs: Vec<MacAddr>,
+    },
+    /// Assign devices to a site
+    Assign {
+        #[clap(long)]
+        org: Option<String>,
+        #[clap(long)]
+        site: Option<String>,
+        #[clap(required = true)]
+        macs: Vec<MacAddr>,
+        /// do not move devices already assigned to another site
+        #[clap(long)]
+        no_reassign: bool,
+        #[clap(long)]
+        disable_auto_config: bool,
+        /// adopt switches/gateways without configuring them
+        #[clap(long)]
+        managed: bool,
+    },
+    /// Unassign devices from their site
+    Unassign {
+        #[clap(long)]
+        org: Option<String>,
+        #[clap(required = true)]
+        macs: Vec<MacAddr>,
+    },
+    /// Replace a device of a site with an unassigned device of the
+    /// inventory, which takes over its name and config
+    Replace {
+        #[clap(long)]
+        org: Option<String>,
+        #[clap(long)]
+        site: Option<String>,
+        /// MAC address of the device being replaced
+        old_mac: MacAddr,
+        /// MAC address of the replacement
+        new_mac: MacAddr,
+        /// comma separated attributes not to copy, e.g. name,notes
+        #[clap(long, use_delimiter = true)]
+        discard: Vec<String>,
+    },
+    /// Convert cloud-ready switches or gateways to Mist management
+    Upgrade {
+        #[clap(long)]
+        org: Option<String>,
+        #[clap(required = true)]
+        macs: Vec<MacAddr>,
+    },
+    /// Count devices per model or site
+    Count {
+        #[clap(long)]
+        org: Option<String>,
+        /// model or site
+        #[clap(long, default_value = "model")]
+        by: CountBy,
+        #[clap(flatten)]
+        filter: ListFilter,
+    },
+    /// Compare the inventory with a CSV of the expected devices with the
+    /// columns serial, mac and site
+    Reconcile {
+        #[clap(long)]
+        org: Option<String>,
+        file: PathBuf,
+        /// assign or unassign the devices on another site than expected
+        #[clap(long)]
+        apply: bool,
+        /// MAC addresses per assign or unassign request
+        #[clap(long, default_value = "100")]
+        batch_size: usize,
+    },
+    /// Search the inventory of every org you have an org privilege on
+    Search {
+        #[clap(flatten)]
+        filter: ListFilter,
+    },
+}
+
+#[derive(Clap, Debug)]
+pub struct ListFilter {
+    /// ap, switch or gateway
+    #[clap(long = "type")]
+    ty: Option<DeviceType>,
+    #[clap(long)]
+    model: Option<String>,
+    #[clap(long)]
+    serial: Option<String>,
+    #[clap(long)]
+    mac: Option<String>,
+    /// devices assigned to this site, by ID or name
+    #[clap(long)]
+    site: Option<String>,
+    /// devices not assigned to any site
+    #[clap(long)]
+    unassigned: bool,
+    /// include the members of virtual chassis
+    #[clap(long)]
+    vc: bool,
+    /// epoch seconds
+    #[clap(long)]
+    modified_after: Option<u64>,
+}
+
+impl ListFilter {
+    fn query(&self) -> InventoryQuery {
+        let mut query = InventoryQuery::new();
+        if let Some(ty) = &self.ty {
+            query = query.ty(ty.clone());
+        }
+        if let Some(model) = &self.model {
+            query = query.model(model);
+        }
+        if let Some(serial) = &self.serial {
+            query = query.serial(serial);
+        }
+        if let Some(mac) = &self.mac {
+            query = query.mac(mac);
+        }
+        if self.unassigned {
+            query = query.unassigned(true);
+        }
+        if self.vc {
+            query = query.vc(true);
+        }
+        if let Some(epoch) = self.modified_after {
+            query = query.modified_after(epoch);
+        }
+        query
+    }
+}
+
+pub fn run(ctx: &Context, cmd: &InventoryCmd) -> Result<(), MistError> {
+    let c = &ctx.client;
+    match cmd {
+        InventoryCmd::List { org, filter } => {
+            let org_id = ctx.org_id(org)?;
+            let mut query = filter.query();
+            if filter.site.is_some() {
+                query = query.site_id(&ctx.org_site_id(&org_id, &filter.site)?);
+            }
+            let pages = inventory::list_pages(c, &org_id, Some(&query))?;
+            ctx.print_pages::<Inventories, _>(pages)
+        }
+        InventoryCmd::Claim { org, codes } => {
+            let codes = codes.iter().map(String::as_str).collect();
+            ctx.print(&inventory::claim(c, &ctx.writable_org_id(org)?, codes)?)
+        }
+        InventoryCmd::ClaimCsv {
+            org,
+            file,
+            report,
+            chunk_size,
+        } => {
+            let org_id = ctx.writable_org_id(org)?;
+            let rows = bulk::read_claims(file)?;
+            let results = bulk::claim_rows(c, &org_id, &rows, *chunk_size, |site| {
+                ctx.org_site_id(&org_id, &Some(site.to_string()))
+            })?;
+            if let Some(path) = report {
+                let mut w = File::create(path)?;
+                output::render(&mut w, &results, OutputFormat::Csv, None)?;
+            }
+            ctx.print(&results)?;
+            match results.failed() {
+                0 => Ok(()),
+                n => Err(MistError::Invalid(format!(
+                    "{} of {} rows failed",
+                    n,
+                    rows.len()
+                ))),
+            }
+        }
+        InventoryCmd::Delete { org, serials, macs } => ctx.print(&inventory::delete(
+            c,
+            &ctx.writable_org_id(org)?,
+            serials.clone(),
+            macs.clone(),
+        )?),
+        InventoryCmd::Assign {
+            org,
+            site,
+            macs,
+            no_reassign,
+            disable_auto_config,
+            managed,
+        } => {
+            let org_id = ctx.writable_org_id(org)?;
+            ctx.print(&inventory::assign(
+                c,
+                &org_id,
+                &ctx.org_site_id(&org_id, site)?,
+                macs.clone(),
+                flag(*no_reassign),
+                flag(*disable_auto_config),
+                flag(*managed),
+            )?)
+        }
+        InventoryCmd::Unassign { org, macs } => ctx.print(&inventory::unassign(
+            c,
+            &ctx.writable_org_id(org)?,
+            macs.clone(),
+        )?),
+        InventoryCmd::Replace {
+            org,
+            site,
+            old_mac,
+            new_mac,
+            discard,
+        } => {
+            let org_id = ctx.writable_org_id(org)?;
+            let req = ReplaceInventory {
+                mac: old_mac.clone(),
+                inventory_mac: new_mac.clone(),
+                site_id: ctx.org_site_id(&org_id, site)?,
+                discard: discard.clone(),
+            };
+            ctx.print(&inventory::replace(c, &org_id, &req)?)
+        }
+        InventoryCmd::Upgrade { org, macs } => ctx.print(&inventory::upgrade_to_mist(
+            c,
+            &ctx.writable_org_id(org)?,
+            macs.clone(),
+        )?),
+        InventoryCmd::Count { org, by, filter } => {
+            let org_id = ctx.org_id(org)?;
+            let mut query = filter.query();
+            if filter.site.is_some() {
+                query = query.site_id(&ctx.org_site_id(&org_id, &filter.site)?);
+            }
+            let counts = inventory::count_all(c, &org_id, *by, Some(&query))?;
+            print_columns(ctx, &counts, &[by.column(), "count"])
+        }
+        InventoryCmd::Reconcile {
+            org,
+            file,
+            apply,
+            batch_size,
+        } => {
+            let org_id = if *apply {
+                ctx.writable_org_id(org)?
+            } else {
+                ctx.org_id(org)?
+            };
+            let expected = reconcile::read_expected(file)?;
+            let found = reconcile::reconcile(c, &org_id, &expected, |site| {
+                ctx.org_site_id(&org_id, &Some(site.to_string()))
+            })?;
+            let applied = if *apply {
+                Some(reconcile::apply(c, &org_id, &found, *batch_size)?)
+            } else {
+                None
+            };
+            let report = Reconciliation {
+                discrepancies: found,
+                applied,
+            };
+            ctx.print(&report)?;
+            // the replies are part of the report in the other formats
+            match &report.applied {
+                Some(applied) if ctx.output == OutputFormat::Table => ctx.print(applied),
+                _ => Ok(()),
+            }
+        }
+        InventoryCmd::Search { filter } => {
+            if filter.site.is_some() {
+                return Err(MistError::Config(
+                    "--site is not supported across orgs, use `inventory list`".into(),
+                ));
+            }
+            let found = inventory::search(c, &filter.query())?;
+            let mut columns = vec!["org_id"];
+            columns.extend(Inventories::default_columns());
+            print_columns(ctx, &found, &columns)
+        }
+    }
+}
+
+// private functions
+
+// prints with `columns` unless --columns was given
+fn print_columns<'a, R: Render>(
+    ctx: &Context,
+    value: &'a R,
+    columns: &'a [&'a str],
+) -> Result<(), MistError> {
+    let columns = ctx
+        .columns
+        .clone()
+        .unwrap_or_else(|| columns.iter().map(|c| c.to_string()).collect());
+    let stdout = io::stdout();
+    let mut w = stdout.lock();
+    output::render(&mut w, value, ctx.output, Some(&columns))
+}
+
+fn flag(set: bool) -> Option<bool> {
+    if set {
+        Some(true)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,92 @@
+// Copyright 2020 Eishun Kondoh
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::cli::Context;
+use crate::error::MistError;
+use crate::logger::info;
+use crate::org::licenses;
+use clap::Clap;
+
+#[derive(Clap, Debug)]
+pub enum LicenseCmd {
+    /// List the subscriptions of an org
+    List {
+        #[clap(long)]
+        org: Option<String>,
+    },
+    /// Claim the subscriptions and devices of an activation code
+    Claim {
+        #[clap(long)]
+        org: Option<String>,
+        code: String,
+    },
+    /// Move licenses of a subscription to another org
+    Move {
+        #[clap(long)]
+        org: Option<String>,
+        subscription_id: String,
+        /// org ID or name to move the licenses to
+        #[clap(long)]
+        to: String,
+        #[clap(long)]
+        quantity: u32,
+    },
+    /// Compare the entitlement with the devices of each type and warn
+    /// about subscriptions ending soon
+    Report {
+        #[clap(long)]
+        org: Option<String>,
+        /// warn about subscriptions ending within these days
+        #[clap(long, default_value = "30")]
+        days: u32,
+    },
+}
+
+pub fn run(ctx: &Context, cmd: &LicenseCmd) -> Result<(), MistError> {
+    let c = &ctx.client;
+    match cmd {
+        LicenseCmd::List { org } => ctx.print(&licenses::list(c, &ctx.org_id(org)?)?),
+        LicenseCmd::Claim { org, code } => {
+            let reply = licenses::claim(c, &ctx.writable_org_id(org)?, code)?;
+            if !reply.inventory_added.is_empty() {
+                info(&format!(
+                    "{} devices added to the inventory",
+                    reply.inventory_added.len()
+                ));
+            }
+            ctx.print(&reply)?;
+            match (reply.license_error.is_empty(), reply.reason.is_empty()) {
+                (true, true) => Ok(()),
+                _ => Err(MistError::Invalid(format!(
+                    "activation code not fully claimed: {}",
+                    reply.reason.join(", ")
+                ))),
+            }
+        }
+        LicenseCmd::Move {
+            org,
+            subscription_id,
+            to,
+            quantity,
+        } => licenses::move_license(
+            c,
+            &ctx.writable_org_id(org)?,
+            subscription_id,
+            &ctx.org_id(&Some(to.clone()))?,
+            *quantity,
+        ),
+        LicenseCmd::Report { org, days } => {
+            let report = licenses::report(c, &ctx.org_id(org)?, *days)?;
+            ctx.print(&report)?;
+            match report.flagged() {
+                0 => Ok(()),
+                n => Err(MistError::Invalid(format!(
+                    "{} license warnings, {} subscriptions end within {} days",
+                    n,
+                    report.expiring.len(),
+                    days
+                ))),
+            }
+        }
+    }
+}
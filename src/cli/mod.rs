@@ -0,0 +1,296 @@
+// Copyright 2020 Eishun Kondoh
+// SPDX-License-Identifier: Apache-2.0
+
+pub mod admin;
+pub mod audit;
+pub mod auth;
+pub mod inventory;
+pub mod license;
+pub mod msp;
+pub mod org;
+pub mod site;
+pub mod token;
+pub mod webhook;
+
+use crate::config::{Config, Profile};
+use crate::error::MistError;
+use crate::http::HttpClient;
+use crate::output::{self, CsvStream, OutputFormat, Render};
+use crate::resolver::Resolver;
+use crate::retry::RetryPolicy;
+use crate::types::{MspId, OrgId, SiteId};
+use clap::Clap;
+use std::cell::RefCell;
+use std::io;
+use std::path::PathBuf;
+
+#[derive(Clap, Debug)]
+#[clap(name = "mist", version = env!("CARGO_PKG_VERSION"))]
+pub struct Opts {
+    /// profile of the config file, defaults to $MIST_PROFILE
+    #[clap(long, global = true)]
+    pub profile: Option<String>,
+    /// config file, defaults to ~/.config/mist/config.toml
+    #[clap(long, global = true, parse(from_os_str))]
+    pub config: Option<PathBuf>,
+    /// table, json, yaml or csv, defaults to the output of the profile
+    #[clap(short, long, global = true)]
+    pub output: Option<OutputFormat>,
+    /// comma separated columns of table and csv output
+    #[clap(long, global = true, use_delimiter = true)]
+    pub columns: Option<Vec<String>>,
+    /// number of items requested per page of list endpoints
+    #[clap(long, global = true)]
+    pub page_size: Option<u32>,
+    /// retries of requests failing with 429, 5xx or connection errors
+    #[clap(long, global = true)]
+    pub retries: Option<u32>,
+    #[clap(subcommand)]
+    pub cmd: Command,
+}
+
+#[derive(Clap, Debug)]
+pub enum Command {
+    /// Sites of an org
+    Site(site::SiteCmd),
+    /// Site groups of an org
+    Sitegroup(site::SiteGroupCmd),
+    /// Org settings, stats and creation
+    Org(org::OrgCmd),
+    /// Device inventory of an org
+    Inventory(inventory::InventoryCmd),
+    /// Admins of an org
+    Admin(admin::AdminCmd),
+    /// Invitations to become an admin of an org
+    Invite(admin::InviteCmd),
+    /// Licenses of an org
+    License(license::LicenseCmd),
+    /// MSPs, their orgs and org groups
+    Msp(msp::MspCmd),
+    /// Webhooks of an org or site
+    Webhook(webhook::WebhookCmd),
+    /// Audit logs of the current user or an org
+    Audit(audit::AuditCmd),
+    /// Login with email and password
+    Login(auth::LoginCmd),
+    /// Tell whether an account uses password or SSO login
+    Lookup(auth::LookupCmd),
+    /// Enroll the current user in two-factor authentication
+    TwoFactor(auth::TwoFactorCmd),
+    /// API tokens of the current user or an org
+    Token(token::TokenCmd),
+    /// Logout the current session
+    Logout,
+    /// Show the current user and its privileges
+    Whoami,
+    /// Show the API calls used and remaining in the hourly quota
+    Budget,
+}
+
+/// Everything a subcommand needs: the API client and the selected profile
+pub struct Context {
+    pub client: HttpClient,
+    pub profile: Profile,
+    // config file given with --config, None for the default path
+    pub config_path: Option<PathBuf>,
+    pub output: OutputFormat,
+    pub columns: Option<Vec<String>>,
+    // org and site names to IDs, and write permission checks
+    resolver: RefCell<Resolver>,
+}
+
+impl Context {
+    pub fn new(opts: &Opts) -> Result<Context, MistError> {
+        let config = Config::load(opts.config.as_deref())?;
+        let profile = config.profile(opts.profile.as_deref())?;
+        let mut builder = profile.client_builder()?;
+        if let Some(page_size) = opts.page_size {
+            builder = builder.page_size(page_size);
+        }
+        if let Some(retries) = opts.retries {
+            builder = builder.retry(RetryPolicy {
+                max_attempts: retries + 1,
+                ..Default::default()
+            });
+        }
+        let client = builder.build()?;
+        let output = match (opts.output, &profile.output) {
+            (Some(output), _) => output,
+            (None, Some(output)) => output.parse()?,
+            (None, None) => OutputFormat::default(),
+        };
+        let resolver = RefCell::new(profile.resolver(client.base_url()));
+        Ok(Context {
+            client,
+            resolver,
+            profile,
+            config_path: opts.config.clone(),
+            output,
+            columns: opts.columns.clone(),
+        })
+    }
+
+    /// Prints a result to stdout in the selected output format
+    pub fn print<R: Render>(&self, value: &R) -> Result<(), MistError> {
+        let stdout = io::stdout();
+        let mut w = stdout.lock();
+        output::render(&mut w, value, self.output, self.columns.as_deref())
+    }
+
+    /// Prints a paginated listing. CSV rows are written as each page
+    /// arrives, the other formats need the whole listing first.
+    pub fn print_pages<R, I>(&self, pages: I) -> Result<(), MistError>
+    where
+        R: Render + From<Vec<<R as Render>::Record>>,
+        I: Iterator<Item = Result<Vec<<R as Render>::Record>, MistError>>,
+    {
+        if self.output == OutputFormat::Csv {
+            let stdout = io::stdout();
+            let mut stream = CsvStream::for_records::<R>(stdout.lock(), self.columns.as_deref());
+            for page in pages {
+                for record in page? {
+                    stream.write_record(&record)?;
+                }
+            }
+            return stream.flush();
+        }
+        let mut records = Vec::new();
+        for page in pages {
+            records.extend(page?);
+        }
+        self.print(&R::from(records))
+    }
+
+    /// org given on the command line, otherwise the one of the profile,
+    /// by ID or by name
+    pub fn org_id(&self, org: &Option<String>) -> Result<OrgId, MistError> {
+        let org = org
+            .clone()
+            .or_else(|| self.profile.org_id.clone())
+            .ok_or_else(|| {
+                MistError::Config("org_id is required: use --org or the profile".into())
+            })?;
+        self.resolver.borrow_mut().org_id(&self.client, &org)
+    }
+
+    /// MSP given on the command line by ID or name, otherwise the only MSP
+    /// the user has a privilege on
+    pub fn msp_id(&self, msp: &Option<String>) -> Result<MspId, MistError> {
+        self.resolver
+            .borrow_mut()
+            .msp_id(&self.client, msp.as_deref())
+    }
+
+    /// Like `msp_id`, refusing up front when the user can not change the MSP
+    pub fn writable_msp_id(&self, msp: &Option<String>) -> Result<MspId, MistError> {
+        let msp_id = self.msp_id(msp)?;
+        self.resolver
+            .borrow_mut()
+            .check_write(&self.client, Some(&msp_id), None, None)?;
+        Ok(msp_id)
+    }
+
+    /// site given on the command line, otherwise the one of the profile,
+    /// by ID or by name. Names are looked up in the org of the profile
+    /// when it has one.
+    pub fn site_id(&self, site: &Option<String>) -> Result<SiteId, MistError> {
+        let site = site
+            .clone()
+            .or_else(|| self.profile.site_id.clone())
+            .ok_or_else(|| {
+                MistError::Config("site_id is required: use --site or the profile".into())
+            })?;
+        let org_id = self.profile_org_id()?;
+        self.resolver
+            .borrow_mut()
+            .site_id(&self.client, org_id.as_ref(), &site)
+    }
+
+    /// site of a known org, by ID or by name
+    pub fn org_site_id<'a>(
+        &self,
+        org_id: &'a OrgId,
+        site: &Option<String>,
+    ) -> Result<SiteId, MistError> {
+        let site = site
+            .clone()
+            .or_else(|| self.profile.site_id.clone())
+            .ok_or_else(|| {
+                MistError::Config("site_id is required: use --site or the profile".into())
+            })?;
+        self.resolver
+            .borrow_mut()
+            .site_id(&self.client, Some(org_id), &site)
+    }
+
+    /// Like `org_id`, refusing up front when the user can not change the org
+    pub fn writable_org_id(&self, org: &Option<String>) -> Result<OrgId, MistError> {
+        let org_id = self.org_id(org)?;
+        self.resolver
+            .borrow_mut()
+            .check_write(&self.client, None, Some(&org_id), None)?;
+        Ok(org_id)
+    }
+
+    /// Like `site_id`, refusing up front when the user can not change the site
+    pub fn writable_site_id(&self, site: &Option<String>) -> Result<SiteId, MistError> {
+        let site_id = self.site_id(site)?;
+        let org_id = self.profile_org_id()?;
+        self.resolver.borrow_mut().check_write(
+            &self.client,
+            None,
+            org_id.as_ref(),
+            Some(&site_id),
+        )?;
+        Ok(site_id)
+    }
+
+    fn profile_org_id(&self) -> Result<Option<OrgId>, MistError> {
+        match &self.profile.org_id {
+            Some(org) => Ok(Some(self.resolver.borrow_mut().org_id(&self.client, org)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+pub fn run(opts: Opts) -> Result<(), MistError> {
+    let ctx = Context::new(&opts)?;
+    match &opts.cmd {
+        Command::Site(cmd) => site::run_site(&ctx, cmd),
+        Command::Sitegroup(cmd) => site::run_group(&ctx, cmd),
+        Command::Org(cmd) => org::run(&ctx, cmd),
+        Command::Inventory(cmd) => inventory::run(&ctx, cmd),
+        Command::Admin(cmd) => admin::run_admin(&ctx, cmd),
+        Command::Invite(cmd) => admin::run_invite(&ctx, cmd),
+        Command::License(cmd) => license::run(&ctx, cmd),
+        Command::Msp(cmd) => msp::run(&ctx, cmd),
+        Command::Webhook(cmd) => webhook::run(&ctx, cmd),
+        Command::Audit(cmd) => audit::run(&ctx, cmd),
+        Command::Login(cmd) => auth::login(&ctx, cmd),
+        Command::Lookup(cmd) => auth::lookup(&ctx, cmd),
+        Command::TwoFactor(cmd) => auth::two_factor(&ctx, cmd),
+        Command::Token(cmd) => token::run(&ctx, cmd),
+        Command::Logout => auth::logout(&ctx),
+        Command::Whoami => auth::whoami(&ctx),
+        Command::Budget => ctx.print(&ctx.client.budget().status()?),
+    }
+}
+
+/// Reads a JSON request body from a file, `-` reads stdin
+pub fn read_json<T>(path: &PathBuf) -> Result<T, MistError>
+where
+    T: serde::de::DeserializeOwned,
+{
+    let content = if path.to_str() == Some("-") {
+        let mut buf = String::new();
+        std::io::Read::read_to_string(&mut std::io::stdin(), &mut buf)?;
+        buf
+    } else {
+        std::fs::read_to_string(path)
+            .map_err(|e| MistError::Config(format!("{}: {}", path.display(), e)))?
+    };
+    serde_json::from_str(&content).map_err(|error| MistError::Decode {
+        error,
+        body: content,
+    })
+}
@@ -0,0 +1,180 @@
+// Copyright 2020 Eishun Kondoh
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::cli::Context;
+use crate::error::MistError;
+use crate::msp::msp::{self, OrgGroup};
+use crate::org::org::Org;
+use crate::types::OrgId;
+use clap::Clap;
+
+#[derive(Clap, Debug)]
+pub enum MspCmd {
+    /// Get an MSP
+    Get {
+        /// MSP ID or name, defaults to the only MSP of your privileges
+        #[clap(long)]
+        msp: Option<String>,
+    },
+    /// List the orgs of an MSP
+    Orgs {
+        #[clap(long)]
+        msp: Option<String>,
+        /// list the stats of the orgs instead
+        #[clap(long)]
+        stats: bool,
+    },
+    /// Create an org managed by an MSP
+    CreateOrg {
+        #[clap(long)]
+        msp: Option<String>,
+        #[clap(long)]
+        name: String,
+        #[clap(long)]
+        session_expiry: Option<u16>,
+        #[clap(long)]
+        alarmtemplate_id: Option<String>,
+        /// comma separated org group ids
+        #[clap(long, use_delimiter = true)]
+        orggroup_ids: Option<Vec<String>>,
+        /// disallow Mist support to access the org
+        #[clap(long)]
+        deny_mist: bool,
+    },
+    /// Devices connected and disconnected in each org of an MSP
+    Rollup {
+        #[clap(long)]
+        msp: Option<String>,
+    },
+    /// Org groups of an MSP
+    Orggroup(OrgGroupCmd),
+}
+
+#[derive(Clap, Debug)]
+pub enum OrgGroupCmd {
+    /// List the org groups of an MSP
+    List {
+        #[clap(long)]
+        msp: Option<String>,
+    },
+    /// Get an org group
+    Get {
+        #[clap(long)]
+        msp: Option<String>,
+        group_id: String,
+    },
+    /// Create an org group
+    Create {
+        #[clap(long)]
+        msp: Option<String>,
+        #[clap(long)]
+        name: String,
+        /// comma separated org IDs or names
+        #[clap(long, use_delimiter = true)]
+        orgs: Vec<String>,
+    },
+    /// Update an org group, only the given fields are changed
+    Update {
+        #[clap(long)]
+        msp: Option<String>,
+        group_id: String,
+        #[clap(long)]
+        name: Option<String>,
+        /// comma separated org IDs or names
+        #[clap(long, use_delimiter = true)]
+        orgs: Option<Vec<String>>,
+    },
+    /// Delete an org group
+    Delete {
+        #[clap(long)]
+        msp: Option<String>,
+        group_id: String,
+    },
+}
+
+pub fn run(ctx: &Context, cmd: &MspCmd) -> Result<(), MistError> {
+    let c = &ctx.client;
+    match cmd {
+        MspCmd::Get { msp } => ctx.print(&msp::get(c, &ctx.msp_id(msp)?)?),
+        MspCmd::Orgs { msp, stats: false } => ctx.print(&msp::list_orgs(c, &ctx.msp_id(msp)?)?),
+        MspCmd::Orgs { msp, stats: true } => ctx.print(&msp::list_org_stats(c, &ctx.msp_id(msp)?)?),
+        MspCmd::CreateOrg {
+            msp,
+            name,
+            session_expiry,
+            alarmtemplate_id,
+            orggroup_ids,
+            deny_mist,
+        } => {
+            let org = Org {
+                id: None,
+                msp_id: None,
+                name: name.clone(),
+                session_expiry: *session_expiry,
+                alarmtemplate_id: alarmtemplate_id.clone(),
+                orggroup_ids: orggroup_ids.clone(),
+                allow_mist: !deny_mist,
+            };
+            ctx.print(&msp::create_org(c, &ctx.writable_msp_id(msp)?, org)?)
+        }
+        MspCmd::Rollup { msp } => ctx.print(&msp::device_rollup(c, &ctx.msp_id(msp)?)?),
+        MspCmd::Orggroup(cmd) => run_orggroup(ctx, cmd),
+    }
+}
+
+// private functions
+
+fn run_orggroup(ctx: &Context, cmd: &OrgGroupCmd) -> Result<(), MistError> {
+    let c = &ctx.client;
+    match cmd {
+        OrgGroupCmd::List { msp } => ctx.print(&msp::list_orggroups(c, &ctx.msp_id(msp)?)?),
+        OrgGroupCmd::Get { msp, group_id } => {
+            ctx.print(&msp::get_orggroup(c, &ctx.msp_id(msp)?, group_id)?)
+        }
+        OrgGroupCmd::Create { msp, name, orgs } => {
+            let group = OrgGroup {
+                id: None,
+                name: name.clone(),
+                org_ids: org_ids(ctx, orgs)?,
+                msp_id: None,
+                created_time: None,
+                modified_time: None,
+            };
+            ctx.print(&msp::create_orggroup(
+                c,
+                &ctx.writable_msp_id(msp)?,
+                &group,
+            )?)
+        }
+        OrgGroupCmd::Update {
+            msp,
+            group_id,
+            name,
+            orgs,
+        } => {
+            let msp_id = ctx.writable_msp_id(msp)?;
+            let current = msp::get_orggroup(c, &msp_id, group_id)?;
+            let group = OrgGroup {
+                id: None,
+                name: name.clone().unwrap_or(current.name),
+                org_ids: match orgs {
+                    Some(orgs) => org_ids(ctx, orgs)?,
+                    None => current.org_ids,
+                },
+                msp_id: None,
+                created_time: None,
+                modified_time: None,
+            };
+            ctx.print(&msp::update_orggroup(c, &msp_id, group_id, &group)?)
+        }
+        OrgGroupCmd::Delete { msp, group_id } => {
+            msp::delete_orggroup(c, &ctx.writable_msp_id(msp)?, group_id)
+        }
+    }
+}
+
+fn org_ids<'a>(ctx: &Context, orgs: &'a [String]) -> Result<Vec<OrgId>, MistError> {
+    orgs.iter()
+        .map(|org| ctx.org_id(&Some(org.clone())))
+        .collect()
+}
@@ -0,0 +1,118 @@
+// Copyright 2020 Eishun Kondoh
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::cli::{read_json, Context};
+use crate::error::MistError;
+use crate::logger::info;
+use crate::org::org::{self, Org, OrgSettingParams};
+use crate::org::verify;
+use crate::output::OutputFormat;
+use clap::Clap;
+use std::path::PathBuf;
+
+#[derive(Clap, Debug)]
+pub enum OrgCmd {
+    /// Org settings
+    Setting(SettingCmd),
+    /// Get the stats of an org
+    Stats {
+        #[clap(long)]
+        org: Option<String>,
+    },
+    /// Create an org
+    Create {
+        #[clap(long)]
+        name: String,
+        #[clap(long)]
+        session_expiry: Option<u16>,
+        #[clap(long)]
+        alarmtemplate_id: Option<String>,
+        /// disallow Mist support to access the org
+        #[clap(long)]
+        deny_mist: bool,
+    },
+    /// Clone an org and print the ID of the new org
+    Clone {
+        #[clap(long)]
+        org: Option<String>,
+        /// name of the new org
+        #[clap(long)]
+        name: String,
+        /// compare the settings, site groups and templates of the clone
+        /// with those of the source
+        #[clap(long)]
+        verify: bool,
+    },
+}
+
+#[derive(Clap, Debug)]
+pub enum SettingCmd {
+    /// Get the settings of an org
+    Get {
+        #[clap(long)]
+        org: Option<String>,
+    },
+    /// Update the settings of an org from a JSON file, `-` reads stdin
+    Set {
+        #[clap(long)]
+        org: Option<String>,
+        #[clap(parse(from_os_str))]
+        file: PathBuf,
+    },
+}
+
+pub fn run(ctx: &Context, cmd: &OrgCmd) -> Result<(), MistError> {
+    let c = &ctx.client;
+    match cmd {
+        OrgCmd::Setting(SettingCmd::Get { org }) => {
+            ctx.print(&org::get_setting(c, &ctx.org_id(org)?)?)
+        }
+        OrgCmd::Setting(SettingCmd::Set { org, file }) => {
+            let params: OrgSettingParams = read_json(file)?;
+            ctx.print(&org::update_setting(
+                c,
+                &ctx.writable_org_id(org)?,
+                &params,
+            )?)
+        }
+        OrgCmd::Stats { org } => ctx.print(&org::get_stats(c, &ctx.org_id(org)?)?),
+        OrgCmd::Create {
+            name,
+            session_expiry,
+            alarmtemplate_id,
+            deny_mist,
+        } => {
+            let new_org = Org {
+                id: None,
+                msp_id: None,
+                name: name.clone(),
+                session_expiry: *session_expiry,
+                alarmtemplate_id: alarmtemplate_id.clone(),
+                orggroup_ids: None,
+                allow_mist: !deny_mist,
+            };
+            ctx.print(&org::create(c, &new_org)?)
+        }
+        OrgCmd::Clone { org, name, verify } => {
+            let source = ctx.org_id(org)?;
+            let copy = org::clone(c, &source, name)?;
+            info(&format!("org {} cloned to {}", source, copy));
+            // the ID is part of the report in the other formats
+            if !verify || ctx.output == OutputFormat::Table {
+                println!("{}", copy);
+            }
+            if !verify {
+                return Ok(());
+            }
+            let report = verify::compare_clone(c, &source, &copy)?;
+            ctx.print(&report)?;
+            if report.is_empty() {
+                return Ok(());
+            }
+            Err(MistError::Invalid(format!(
+                "clone {} differs from {}",
+                copy, source
+            )))
+        }
+    }
+}
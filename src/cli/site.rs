@@ -0,0 +1,235 @@
+// Copyright 2020 Eishun Kondoh
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::cli::{read_json, Context};
+use crate::error::MistError;
+use crate::sites::sites::{self, Site, SiteGroup, SiteSetting};
+use crate::types::SiteId;
+use clap::Clap;
+use std::path::PathBuf;
+
+#[derive(Clap, Debug)]
+pub enum SiteCmd {
+    /// List the sites of an org
+    List {
+        #[clap(long)]
+        org: Option<String>,
+    },
+    /// Get a site
+    Get { site_id: Option<String> },
+    /// Create a site in an org
+    Create {
+        #[clap(long)]
+        org: Option<String>,
+        #[clap(flatten)]
+        params: SiteParams,
+    },
+    /// Update a site, only the given fields are changed
+    Update {
+        site_id: Option<String>,
+        #[clap(flatten)]
+        params: SiteParams,
+    },
+    /// Delete a site, its devices go back to the inventory unassigned
+    Delete {
+        site_id: String,
+        /// delete the site even when devices are assigned to it
+        #[clap(long)]
+        force: bool,
+    },
+    /// Get the stats of a site
+    Stats { site_id: Option<String> },
+    /// Site settings
+    Setting(SiteSettingCmd),
+}
+
+#[derive(Clap, Debug)]
+pub enum SiteSettingCmd {
+    /// Get the settings of a site
+    Get { site_id: Option<String> },
+    /// Update the settings of a site from a JSON file, `-` reads stdin.
+    /// Settings missing from the file are left as is.
+    Set {
+        #[clap(long)]
+        site: Option<String>,
+        #[clap(parse(from_os_str))]
+        file: PathBuf,
+    },
+}
+
+#[derive(Clap, Debug)]
+pub struct SiteParams {
+    #[clap(long)]
+    name: Option<String>,
+    #[clap(long)]
+    timezone: Option<String>,
+    #[clap(long)]
+    country_code: Option<String>,
+    #[clap(long)]
+    address: Option<String>,
+    #[clap(long)]
+    notes: Option<String>,
+    /// comma separated site group ids
+    #[clap(long, use_delimiter = true)]
+    sitegroup_ids: Option<Vec<String>>,
+}
+
+#[derive(Clap, Debug)]
+pub enum SiteGroupCmd {
+    /// List the site groups of an org
+    List {
+        #[clap(long)]
+        org: Option<String>,
+    },
+    /// Get a site group
+    Get {
+        group_id: String,
+        #[clap(long)]
+        org: Option<String>,
+    },
+    /// Create a site group
+    Create {
+        #[clap(long)]
+        org: Option<String>,
+        #[clap(long)]
+        name: String,
+        /// comma separated site ids
+        #[clap(long, use_delimiter = true)]
+        site_ids: Vec<SiteId>,
+    },
+    /// Update a site group, only the given fields are changed
+    Update {
+        group_id: String,
+        #[clap(long)]
+        org: Option<String>,
+        #[clap(long)]
+        name: Option<String>,
+        /// comma separated site ids
+        #[clap(long, use_delimiter = true)]
+        site_ids: Option<Vec<SiteId>>,
+    },
+    /// Delete a site group
+    Delete {
+        group_id: String,
+        #[clap(long)]
+        org: Option<String>,
+    },
+}
+
+impl SiteParams {
+    fn apply(&self, site: &mut Site) {
+        if let Some(name) = &self.name {
+            site.name = name.clone();
+        }
+        let fields = [
+            (&self.timezone, &mut site.timezone),
+            (&self.country_code, &mut site.country_code),
+            (&self.address, &mut site.address),
+            (&self.notes, &mut site.notes),
+        ];
+        for (value, field) in fields {
+            if value.is_some() {
+                *field = value.clone();
+            }
+        }
+        if self.sitegroup_ids.is_some() {
+            site.sitegroup_ids = self.sitegroup_ids.clone();
+        }
+    }
+}
+
+pub fn run_site(ctx: &Context, cmd: &SiteCmd) -> Result<(), MistError> {
+    let c = &ctx.client;
+    match cmd {
+        SiteCmd::List { org } => ctx.print(&sites::list_all(c, &ctx.org_id(org)?)?),
+        SiteCmd::Get { site_id } => ctx.print(&sites::get(c, &ctx.site_id(site_id)?)?),
+        SiteCmd::Create { org, params } => {
+            let name = params
+                .name
+                .clone()
+                .ok_or_else(|| MistError::Config("--name is required".into()))?;
+            let mut site = Site {
+                name,
+                ..Default::default()
+            };
+            params.apply(&mut site);
+            ctx.print(&sites::create(c, &ctx.writable_org_id(org)?, &site)?)
+        }
+        SiteCmd::Update { site_id, params } => {
+            let site_id = ctx.writable_site_id(site_id)?;
+            let mut site = sites::get(c, &site_id)?;
+            params.apply(&mut site);
+            ctx.print(&sites::update(c, &site_id, &site)?)
+        }
+        SiteCmd::Delete { site_id, force } => {
+            let site_id = ctx.writable_site_id(&Some(site_id.clone()))?;
+            let stats = sites::get_stats(c, &site_id)?;
+            if stats.num_devices > 0 && !force {
+                return Err(MistError::Invalid(format!(
+                    "site {} has {} devices assigned, which would go back to the inventory \
+                     unassigned; use --force to delete it anyway",
+                    stats.name, stats.num_devices
+                )));
+            }
+            sites::delete(c, &site_id)
+        }
+        SiteCmd::Stats { site_id } => ctx.print(&sites::get_stats(c, &ctx.site_id(site_id)?)?),
+        SiteCmd::Setting(SiteSettingCmd::Get { site_id }) => {
+            ctx.print(&sites::get_setting(c, &ctx.site_id(site_id)?)?)
+        }
+        SiteCmd::Setting(SiteSettingCmd::Set { site, file }) => {
+            let setting: SiteSetting = read_json(file)?;
+            ctx.print(&sites::update_setting(
+                c,
+                &ctx.writable_site_id(site)?,
+                &setting,
+            )?)
+        }
+    }
+}
+
+pub fn run_group(ctx: &Context, cmd: &SiteGroupCmd) -> Result<(), MistError> {
+    let c = &ctx.client;
+    match cmd {
+        SiteGroupCmd::List { org } => ctx.print(&sites::list_group_all(c, &ctx.org_id(org)?)?),
+        SiteGroupCmd::Get { group_id, org } => {
+            ctx.print(&sites::get_group(c, &ctx.org_id(org)?, group_id)?)
+        }
+        SiteGroupCmd::Create {
+            org,
+            name,
+            site_ids,
+        } => {
+            let group = SiteGroup {
+                site_ids: site_ids.clone(),
+                id: None,
+                name: name.clone(),
+                org_id: None,
+                created_time: None,
+                modified_time: None,
+            };
+            ctx.print(&sites::create_group(c, &ctx.writable_org_id(org)?, &group)?)
+        }
+        SiteGroupCmd::Update {
+            group_id,
+            org,
+            name,
+            site_ids,
+        } => {
+            let org_id = ctx.writable_org_id(org)?;
+            let current = sites::get_group(c, &org_id, group_id)?;
+            let group = SiteGroup {
+                site_ids: site_ids.clone().unwrap_or(current.site_ids),
+                id: None,
+                name: name.clone().unwrap_or(current.name),
+                org_id: None,
+                created_time: None,
+                modified_time: None,
+            };
+            ctx.print(&sites::update_group(c, &org_id, group_id, &group)?)
+        }
+        SiteGroupCmd::Delete { group_id, org } => {
+            sites::delete_group(c, &ctx.writable_org_id(org)?, group_id)
+        }
+    }
+}
@@ -0,0 +1,169 @@
+// Copyright 2020 Eishun Kondoh
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::auth::tokens::{self, ApiToken, ApiTokenParams, TokenPrivilege};
+use crate::cli::Context;
+use crate::config::Config;
+use crate::error::MistError;
+use crate::logger::{info, warn};
+use crate::types::{OrgId, Role, Scope};
+use clap::Clap;
+use std::env;
+
+#[derive(Clap, Debug)]
+pub enum TokenCmd {
+    /// List API tokens
+    List {
+        /// org tokens of this org instead of the user tokens
+        #[clap(long)]
+        org: Option<String>,
+    },
+    /// Create an API token, the key is only shown once
+    Create {
+        #[clap(long)]
+        org: Option<String>,
+        #[clap(long)]
+        name: Option<String>,
+        /// scope:role[:id], e.g. org:admin, site:write:<site_id>,
+        /// sitegroup:read:<sitegroup_id>; required for org tokens
+        #[clap(long = "privilege", parse(try_from_str = parse_privilege))]
+        privileges: Vec<TokenPrivilege>,
+        /// comma separated source IPs allowed to use the token
+        #[clap(long, use_delimiter = true)]
+        src_ips: Option<Vec<String>>,
+    },
+    /// Delete an API token
+    Delete {
+        #[clap(long)]
+        org: Option<String>,
+        id: String,
+    },
+    /// Replace the token of the profile with a new one and delete the old
+    Rotate {
+        #[clap(long)]
+        org: Option<String>,
+        /// id of the token to rotate, defaults to the token of the profile
+        #[clap(long)]
+        id: Option<String>,
+    },
+}
+
+pub fn run(ctx: &Context, cmd: &TokenCmd) -> Result<(), MistError> {
+    let c = &ctx.client;
+    match cmd {
+        TokenCmd::List { org } => match org_id(ctx, org, false)? {
+            Some(org_id) => ctx.print(&tokens::list_org(c, &org_id)?),
+            None => ctx.print(&tokens::list(c)?),
+        },
+        TokenCmd::Create {
+            org,
+            name,
+            privileges,
+            src_ips,
+        } => {
+            let params = ApiTokenParams {
+                name: name.clone(),
+                privileges: privileges.clone(),
+                src_ips: src_ips.clone(),
+            };
+            let token = match org_id(ctx, org, true)? {
+                Some(org_id) => tokens::create_org(c, &org_id, &params)?,
+                None => tokens::create(c, &params)?,
+            };
+            ctx.print(&token)
+        }
+        TokenCmd::Delete { org, id } => match org_id(ctx, org, true)? {
+            Some(org_id) => tokens::delete_org(c, &org_id, id),
+            None => tokens::delete(c, id),
+        },
+        TokenCmd::Rotate { org, id } => {
+            let org_id = org_id(ctx, org, true)?;
+            rotate(ctx, org_id.as_ref(), id.as_deref())
+        }
+    }
+}
+
+// private functions
+
+fn rotate<'a>(
+    ctx: &Context,
+    org_id: Option<&'a OrgId>,
+    id: Option<&'a str>,
+) -> Result<(), MistError> {
+    let c = &ctx.client;
+    let listing = match org_id {
+        Some(org_id) => tokens::list_org(c, org_id)?,
+        None => tokens::list(c)?,
+    };
+    let current = ctx
+        .profile
+        .token
+        .as_deref()
+        .and_then(|key| tokens::find_current(&listing, key).ok());
+    let old_id = match (id, current) {
+        (Some(id), _) => id.to_string(),
+        (None, Some(token)) => token.id.clone(),
+        (None, None) => {
+            return Err(MistError::Invalid(
+                "the token of the profile is not in the listing, give the id to rotate".into(),
+            ))
+        }
+    };
+    // only the token of the profile is written back to it
+    let of_profile = current.is_some_and(|token| token.id == old_id);
+    let new = tokens::rotate(c, org_id, &old_id, |token| {
+        if of_profile {
+            save_token(ctx, token)
+        } else {
+            Ok(())
+        }
+    })?;
+    ctx.print(&new)
+}
+
+// org tokens are only meant when --org is given, never the profile's org
+fn org_id(ctx: &Context, org: &Option<String>, write: bool) -> Result<Option<OrgId>, MistError> {
+    match org {
+        Some(_) if write => Ok(Some(ctx.writable_org_id(org)?)),
+        Some(_) => Ok(Some(ctx.org_id(org)?)),
+        None => Ok(None),
+    }
+}
+
+fn save_token<'a>(ctx: &Context, token: &'a ApiToken) -> Result<(), MistError> {
+    let mut config = Config::load(ctx.config_path.as_deref())?;
+    config.set_token(&ctx.profile.name, &token.key);
+    config.save(ctx.config_path.as_deref())?;
+    info(&format!("token of profile {} updated", ctx.profile.name));
+    if env::var("MIST_TOKEN").is_ok() {
+        warn("MIST_TOKEN is set and overrides the token saved in the profile");
+    }
+    Ok(())
+}
+
+pub(crate) fn parse_privilege(s: &str) -> Result<TokenPrivilege, MistError> {
+    let parts: Vec<&str> = s.split(':').collect();
+    let (scope, role, id) = match parts.as_slice() {
+        [scope, role] => (scope.parse::<Scope>()?, role.parse::<Role>()?, None),
+        [scope, role, id] => (scope.parse()?, role.parse()?, Some(*id)),
+        _ => return Err(MistError::Invalid(format!("invalid privilege: {}", s))),
+    };
+    let mut privilege = TokenPrivilege {
+        scope: scope.clone(),
+        role,
+        site_id: None,
+        sitegroup_id: None,
+    };
+    match (scope, id) {
+        (Scope::Org, None) => {}
+        (Scope::Site, Some(id)) => privilege.site_id = Some(id.parse()?),
+        (Scope::Sitegroup, Some(id)) => privilege.sitegroup_id = Some(id.to_string()),
+        _ => {
+            return Err(MistError::Invalid(format!(
+                "invalid privilege {}: use org:<role>, site:<role>:<id> or sitegroup:<role>:<id>",
+                s
+            )))
+        }
+    }
+    Ok(privilege)
+}
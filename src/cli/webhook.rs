@@ -0,0 +1,155 @@
+// Copyright 2020 Eishun Kondoh
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::cli::Context;
+use crate::error::MistError;
+use crate::types::WebhookTopic;
+use crate::webhooks::webhooks::{self, Webhook, WebhookOwner};
+use clap::Clap;
+
+#[derive(Clap, Debug)]
+pub enum WebhookCmd {
+    /// List the webhooks of an org or site
+    List {
+        #[clap(flatten)]
+        owner: OwnerOpts,
+    },
+    /// Get a webhook
+    Get {
+        #[clap(flatten)]
+        owner: OwnerOpts,
+        webhook_id: String,
+    },
+    /// Create a webhook
+    Create {
+        #[clap(flatten)]
+        owner: OwnerOpts,
+        #[clap(flatten)]
+        params: WebhookParams,
+    },
+    /// Update a webhook, only the given fields are changed
+    Update {
+        #[clap(flatten)]
+        owner: OwnerOpts,
+        webhook_id: String,
+        #[clap(flatten)]
+        params: WebhookParams,
+    },
+    /// Delete a webhook
+    Delete {
+        #[clap(flatten)]
+        owner: OwnerOpts,
+        webhook_id: String,
+    },
+    /// Send a test delivery to a webhook
+    Ping {
+        #[clap(flatten)]
+        owner: OwnerOpts,
+        webhook_id: String,
+    },
+}
+
+#[derive(Clap, Debug)]
+pub struct OwnerOpts {
+    #[clap(long, conflicts_with = "site")]
+    org: Option<String>,
+    /// webhooks of a site instead of the org
+    #[clap(long)]
+    site: Option<String>,
+}
+
+#[derive(Clap, Debug)]
+pub struct WebhookParams {
+    #[clap(long)]
+    name: Option<String>,
+    #[clap(long)]
+    url: Option<String>,
+    /// secret signing the deliveries
+    #[clap(long)]
+    secret: Option<String>,
+    /// comma separated topics: alarms, audits, device-events, client-join,
+    /// location, zone, asset-raw
+    #[clap(long, use_delimiter = true)]
+    topics: Option<Vec<WebhookTopic>>,
+    /// true or false, true by default
+    #[clap(long)]
+    verify_cert: Option<bool>,
+    /// true or false, true by default
+    #[clap(long)]
+    enabled: Option<bool>,
+}
+
+impl OwnerOpts {
+    fn owner(&self, ctx: &Context) -> Result<WebhookOwner, MistError> {
+        match &self.site {
+            Some(_) => Ok(WebhookOwner::Site(ctx.site_id(&self.site)?)),
+            None => Ok(WebhookOwner::Org(ctx.org_id(&self.org)?)),
+        }
+    }
+
+    fn writable_owner(&self, ctx: &Context) -> Result<WebhookOwner, MistError> {
+        match &self.site {
+            Some(_) => Ok(WebhookOwner::Site(ctx.writable_site_id(&self.site)?)),
+            None => Ok(WebhookOwner::Org(ctx.writable_org_id(&self.org)?)),
+        }
+    }
+}
+
+impl WebhookParams {
+    fn apply(&self, webhook: &mut Webhook) {
+        if let Some(name) = &self.name {
+            webhook.name = name.clone();
+        }
+        if let Some(url) = &self.url {
+            webhook.url = url.clone();
+        }
+        if self.secret.is_some() {
+            webhook.secret = self.secret.clone();
+        }
+        if let Some(topics) = &self.topics {
+            webhook.topics = topics.clone();
+        }
+        if let Some(verify_cert) = self.verify_cert {
+            webhook.verify_cert = verify_cert;
+        }
+        if let Some(enabled) = self.enabled {
+            webhook.enabled = enabled;
+        }
+    }
+}
+
+pub fn run(ctx: &Context, cmd: &WebhookCmd) -> Result<(), MistError> {
+    let c = &ctx.client;
+    match cmd {
+        WebhookCmd::List { owner } => ctx.print(&webhooks::list(c, &owner.owner(ctx)?)?),
+        WebhookCmd::Get { owner, webhook_id } => {
+            ctx.print(&webhooks::get(c, &owner.owner(ctx)?, webhook_id)?)
+        }
+        WebhookCmd::Create { owner, params } => {
+            let (name, url) = match (&params.name, &params.url) {
+                (Some(name), Some(url)) => (name, url),
+                _ => return Err(MistError::Config("--name and --url are required".into())),
+            };
+            let mut webhook = Webhook::new(name, url, Vec::new());
+            params.apply(&mut webhook);
+            if webhook.topics.is_empty() {
+                return Err(MistError::Config("--topics is required".into()));
+            }
+            ctx.print(&webhooks::create(c, &owner.writable_owner(ctx)?, &webhook)?)
+        }
+        WebhookCmd::Update {
+            owner,
+            webhook_id,
+            params,
+        } => {
+            let owner = owner.writable_owner(ctx)?;
+            let mut webhook = webhooks::get(c, &owner, webhook_id)?;
+            params.apply(&mut webhook);
+            ctx.print(&webhooks::update(c, &owner, webhook_id, &webhook)?)
+        }
+        WebhookCmd::Delete { owner, webhook_id } => {
+            webhooks::delete(c, &owner.writable_owner(ctx)?, webhook_id)
+        }
+        WebhookCmd::Ping { owner, webhook_id } => webhooks::ping(c, &owner.owner(ctx)?, webhook_id),
+    }
+}
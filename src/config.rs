@@ -0,0 +1,269 @@
+// Copyright 2020 Eishun Kondoh
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::budget::BudgetConfig;
+use crate::consts::Cloud;
+use crate::error::MistError;
+use crate::http::{HttpClient, HttpClientBuilder};
+use crate::logger::debug;
+use crate::resolver::{Resolver, DEFAULT_TTL};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub const DEFAULT_PROFILE: &'static str = "default";
+
+/// ~/.config/mist/config.toml
+///
+/// ```toml
+/// default_profile = "lab"
+///
+/// [profiles.lab]
+/// cloud = "global01"
+/// token = "..."
+/// org_id = "..."
+///
+/// [profiles.customer-a]
+/// host = "api.eu.mist.com"
+/// email = "admin@example.com"
+/// output = "json"
+/// throttle_at = 0.8
+/// budget_file = "/var/tmp/mist-budget.json"
+/// session_file = "/home/me/.cache/mist/session-customer-a.json"
+/// cache_ttl = 600
+/// ```
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Config {
+    #[serde(default)]
+    pub default_profile: Option<String>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct Profile {
+    // profile name, filled in when the profile is selected
+    #[serde(skip)]
+    pub name: String,
+    // Mist cloud region, e.g. global01, emea01, apac01, govcloud
+    #[serde(default)]
+    pub cloud: Option<String>,
+    // arbitrary API host, takes precedence over cloud
+    #[serde(default)]
+    pub host: Option<String>,
+    // API token
+    #[serde(default)]
+    pub token: Option<String>,
+    // login email for password authentication
+    #[serde(default)]
+    pub email: Option<String>,
+    // org used when a command is not given one
+    #[serde(default)]
+    pub org_id: Option<String>,
+    // site used when a command is not given one
+    #[serde(default)]
+    pub site_id: Option<String>,
+    // output format, e.g. table, json
+    #[serde(default)]
+    pub output: Option<String>,
+    // fraction of the hourly quota after which requests are paced
+    #[serde(default)]
+    pub throttle_at: Option<f64>,
+    // file sharing the hourly budget between processes using the token
+    #[serde(default)]
+    pub budget_file: Option<String>,
+    // file keeping the cookies of `mist login`, defaults to
+    // ~/.cache/mist/session-<profile>.json
+    #[serde(default)]
+    pub session_file: Option<String>,
+    // seconds org and site names stay cached, defaults to an hour
+    #[serde(default)]
+    pub cache_ttl: Option<u64>,
+}
+
+impl Config {
+    /// `$MIST_CONFIG`, otherwise `$XDG_CONFIG_HOME/mist/config.toml`,
+    /// otherwise `~/.config/mist/config.toml`
+    pub fn default_path() -> Option<PathBuf> {
+        if let Ok(path) = env::var("MIST_CONFIG") {
+            return Some(PathBuf::from(path));
+        }
+        env::var("XDG_CONFIG_HOME")
+            .ok()
+            .map(PathBuf::from)
+            .or_else(|| dirs::home_dir().map(|home| home.join(".config")))
+            .map(|dir| dir.join("mist").join("config.toml"))
+    }
+
+    /// Reads the config file, a missing file is an empty config.
+    pub fn load(path: Option<&Path>) -> Result<Config, MistError> {
+        let path = match path.map(Path::to_path_buf).or_else(Config::default_path) {
+            Some(path) if path.exists() => path,
+            _ => {
+                debug("config file not found, using environment only");
+                return Ok(Config::default());
+            }
+        };
+        let content = fs::read_to_string(&path)
+            .map_err(|e| MistError::Config(format!("{}: {}", path.display(), e)))?;
+        toml::from_str(&content)
+            .map_err(|e| MistError::Config(format!("{}: {}", path.display(), e)))
+    }
+
+    /// Writes the config file back, readable by the owner only since it
+    /// holds tokens. Comments of a hand written file are not preserved.
+    pub fn save(&self, path: Option<&Path>) -> Result<(), MistError> {
+        let path = path
+            .map(Path::to_path_buf)
+            .or_else(Config::default_path)
+            .ok_or_else(|| MistError::Config("no config file path".into()))?;
+        let content = toml::to_string(self)
+            .map_err(|e| MistError::Config(format!("{}: {}", path.display(), e)))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // write then rename, so a failed write never truncates the config
+        let tmp = path.with_extension("tmp");
+        let _ = fs::remove_file(&tmp);
+        write_private(&tmp, &content)?;
+        fs::rename(&tmp, &path)?;
+        debug(&format!("config saved to {}", path.display()));
+        Ok(())
+    }
+
+    /// Sets the token of a profile, creating the profile when missing
+    pub fn set_token<'a>(&mut self, profile: &'a str, token: &'a str) {
+        self.profiles.entry(profile.to_string()).or_default().token = Some(token.to_string());
+    }
+
+    /// Selects a profile by, in order, `name`, `$MIST_PROFILE`,
+    /// `default_profile` and "default", then applies the `MIST_*`
+    /// environment overrides on top of it.
+    pub fn profile<'a>(&self, name: Option<&'a str>) -> Result<Profile, MistError> {
+        let requested = name
+            .map(str::to_string)
+            .or_else(|| env::var("MIST_PROFILE").ok());
+        let name = requested
+            .clone()
+            .or_else(|| self.default_profile.clone())
+            .unwrap_or_else(|| DEFAULT_PROFILE.to_string());
+        let mut profile = match self.profiles.get(&name) {
+            Some(profile) => profile.clone(),
+            None if requested.is_some() => {
+                return Err(MistError::Config(format!(
+                    "profile {} is not defined",
+                    name
+                )))
+            }
+            None => Profile::default(),
+        };
+        profile.name = name;
+        profile.apply_env();
+        Ok(profile)
+    }
+}
+
+impl Profile {
+    pub fn client_builder(&self) -> Result<HttpClientBuilder, MistError> {
+        let mut builder = HttpClient::builder();
+        if let Some(host) = &self.host {
+            builder = builder.base_url(host);
+        } else if let Some(cloud) = &self.cloud {
+            builder = builder.cloud(cloud.parse::<Cloud>()?);
+        }
+        if let Some(token) = &self.token {
+            builder = builder.token(token);
+        }
+        builder = builder.budget(BudgetConfig {
+            throttle_at: self.throttle_at,
+            state_file: self.budget_file.as_ref().map(PathBuf::from),
+            ..Default::default()
+        });
+        if let Some(path) = self.session_path() {
+            builder = builder.session_file(path);
+        }
+        Ok(builder)
+    }
+
+    /// `session_file`, otherwise a file per profile in the cache dir
+    pub fn session_path(&self) -> Option<PathBuf> {
+        match &self.session_file {
+            Some(path) => Some(PathBuf::from(path)),
+            None => self.cache_path("session"),
+        }
+    }
+
+    /// Resolver of org and site names, cached per profile
+    pub fn resolver<'a>(&self, base: &'a str) -> Resolver {
+        let ttl = self
+            .cache_ttl
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_TTL);
+        Resolver::new(base, self.cache_path("resolve"), ttl)
+    }
+
+    pub fn client(&self) -> Result<HttpClient, MistError> {
+        self.client_builder()?.build()
+    }
+
+    // ~/.cache/mist/<kind>-<profile>.json
+    fn cache_path<'a>(&self, kind: &'a str) -> Option<PathBuf> {
+        let name = if self.name.is_empty() {
+            DEFAULT_PROFILE
+        } else {
+            &self.name
+        };
+        dirs::cache_dir().map(|dir| dir.join("mist").join(format!("{}-{}.json", kind, name)))
+    }
+
+    fn apply_env(&mut self) {
+        // a cloud chosen from the environment wins over the host of the file
+        if env::var("MIST_CLOUD").is_ok() && env::var("MIST_HOST").is_err() {
+            self.host = None;
+        }
+        let vars: [(&str, &mut Option<String>); 9] = [
+            ("MIST_CLOUD", &mut self.cloud),
+            ("MIST_HOST", &mut self.host),
+            ("MIST_TOKEN", &mut self.token),
+            ("MIST_EMAIL", &mut self.email),
+            ("MIST_ORG_ID", &mut self.org_id),
+            ("MIST_SITE_ID", &mut self.site_id),
+            ("MIST_OUTPUT", &mut self.output),
+            ("MIST_BUDGET_FILE", &mut self.budget_file),
+            ("MIST_SESSION_FILE", &mut self.session_file),
+        ];
+        for (var, field) in vars {
+            if let Ok(value) = env::var(var) {
+                if !value.is_empty() {
+                    *field = Some(value);
+                }
+            }
+        }
+    }
+}
+
+// private functions
+
+/// Writes a file readable by the owner only
+#[cfg(unix)]
+pub(crate) fn write_private<'a>(path: &'a Path, content: &'a str) -> Result<(), MistError> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    file.write_all(content.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub(crate) fn write_private<'a>(path: &'a Path, content: &'a str) -> Result<(), MistError> {
+    fs::write(path, content)?;
+    Ok(())
+}
@@ -1,4 +1,79 @@
 // Copyright 2020 Eishun Kondoh
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::error::MistError;
+use std::str::FromStr;
+
 pub const MIST_API_BASE: &'static str = "https://api.mist.com/api/v1";
+pub const MIST_API_PATH: &'static str = "api/v1";
+pub const MIST_USER_AGENT: &'static str = concat!("mist/", env!("CARGO_PKG_VERSION"));
+
+///
+/// ref: https://www.juniper.net/documentation/us/en/software/mist/mist-management/topics/ref/mist-api-endpoints.html
+///
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Cloud {
+    #[default]
+    Global01,
+    Global02,
+    Global03,
+    Global04,
+    Global05,
+    Emea01,
+    Emea02,
+    Emea03,
+    Apac01,
+    Apac02,
+    Apac03,
+    GovCloud,
+}
+
+impl Cloud {
+    pub fn host(&self) -> &'static str {
+        match self {
+            Cloud::Global01 => "api.mist.com",
+            Cloud::Global02 => "api.gc1.mist.com",
+            Cloud::Global03 => "api.ac2.mist.com",
+            Cloud::Global04 => "api.gc2.mist.com",
+            Cloud::Global05 => "api.gc4.mist.com",
+            Cloud::Emea01 => "api.eu.mist.com",
+            Cloud::Emea02 => "api.gc3.mist.com",
+            Cloud::Emea03 => "api.ac6.mist.com",
+            Cloud::Apac01 => "api.ac5.mist.com",
+            Cloud::Apac02 => "api.gc5.mist.com",
+            Cloud::Apac03 => "api.gc7.mist.com",
+            Cloud::GovCloud => "api.us.mist-federal.com",
+        }
+    }
+
+    pub fn api_base(&self) -> String {
+        format!("https://{}/{}", self.host(), MIST_API_PATH)
+    }
+}
+
+impl FromStr for Cloud {
+    type Err = MistError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name: String = s
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase();
+        match &*name {
+            "global" | "global01" | "global1" => Ok(Cloud::Global01),
+            "global02" | "global2" => Ok(Cloud::Global02),
+            "global03" | "global3" => Ok(Cloud::Global03),
+            "global04" | "global4" => Ok(Cloud::Global04),
+            "global05" | "global5" => Ok(Cloud::Global05),
+            "eu" | "emea" | "emea01" | "emea1" => Ok(Cloud::Emea01),
+            "emea02" | "emea2" => Ok(Cloud::Emea02),
+            "emea03" | "emea3" => Ok(Cloud::Emea03),
+            "apac" | "apac01" | "apac1" => Ok(Cloud::Apac01),
+            "apac02" | "apac2" => Ok(Cloud::Apac02),
+            "apac03" | "apac3" => Ok(Cloud::Apac03),
+            "gov" | "govcloud" | "usgov" => Ok(Cloud::GovCloud),
+            _ => Err(MistError::Config(format!("unknown Mist cloud: {}", s))),
+        }
+    }
+}
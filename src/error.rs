@@ -0,0 +1,199 @@
+// Copyright 2020 Eishun Kondoh
+// SPDX-License-Identifier: Apache-2.0
+
+use reqwest::{header::HeaderMap, header::RETRY_AFTER, StatusCode};
+use std::error::Error;
+use std::fmt;
+use std::time::{Duration, SystemTime};
+
+#[derive(Debug)]
+pub enum MistError {
+    // the request could not be sent or the response could not be read
+    Transport(reqwest::Error),
+    // the API answered with a non-2xx status code
+    Status {
+        status: StatusCode,
+        detail: Option<String>,
+        body: String,
+        // delay requested by the `Retry-After` header of 429 and 503
+        retry_after: Option<Duration>,
+    },
+    // the response body does not match the expected type
+    Decode {
+        error: serde_json::Error,
+        body: String,
+    },
+    // the credentials are missing, incorrect or expired
+    Auth {
+        status: Option<StatusCode>,
+        detail: Option<String>,
+    },
+    // the password was accepted, the login needs a two-factor code
+    TwoFactorRequired,
+    // the client or profile configuration is invalid
+    Config(String),
+    // an argument was rejected before sending the request
+    Invalid(String),
+    // reading or writing a local file failed
+    Io(std::io::Error),
+}
+
+impl MistError {
+    /// Builds the error for a non-2xx response, keeping the `detail`
+    /// message Mist puts in the JSON body when there is one.
+    pub fn from_response(status: StatusCode, headers: &HeaderMap, body: String) -> Self {
+        let detail = detail_of(&body);
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => MistError::Auth {
+                status: Some(status),
+                detail,
+            },
+            _ => MistError::Status {
+                status,
+                detail,
+                body,
+                retry_after: retry_after_of(headers),
+            },
+        }
+    }
+
+    pub fn auth<'a>(detail: &'a str) -> Self {
+        MistError::Auth {
+            status: None,
+            detail: Some(detail.to_string()),
+        }
+    }
+
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            MistError::Transport(e) => e.status(),
+            MistError::Status { status, .. } => Some(*status),
+            MistError::Auth { status, .. } => *status,
+            MistError::Decode { .. }
+            | MistError::TwoFactorRequired
+            | MistError::Config(_)
+            | MistError::Invalid(_)
+            | MistError::Io(_) => None,
+        }
+    }
+
+    pub fn detail(&self) -> Option<&str> {
+        match self {
+            MistError::Status { detail, .. } | MistError::Auth { detail, .. } => detail.as_deref(),
+            _ => None,
+        }
+    }
+
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            MistError::Status { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+
+    /// true when sending the same request again may succeed:
+    /// connection failures, timeouts, 429 and 5xx.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            MistError::Transport(e) => e.is_connect() || e.is_timeout(),
+            MistError::Status { status, .. } => {
+                *status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+            }
+            _ => false,
+        }
+    }
+
+    /// true when the caller has to (re-)login or fix its token.
+    pub fn is_auth(&self) -> bool {
+        matches!(self, MistError::Auth { .. })
+    }
+
+    pub fn is_two_factor_required(&self) -> bool {
+        matches!(self, MistError::TwoFactorRequired)
+    }
+}
+
+impl fmt::Display for MistError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MistError::Transport(e) => write!(f, "transport error: {}", e),
+            MistError::Status {
+                status,
+                detail: Some(detail),
+                ..
+            } => write!(f, "HTTP {}: {}", status, detail),
+            MistError::Status { status, body, .. } => write!(f, "HTTP {}: {}", status, body),
+            MistError::Decode { error, body } => {
+                write!(f, "unexpected response ({}): {}", error, body)
+            }
+            MistError::Auth {
+                status: Some(status),
+                detail: Some(detail),
+            } => write!(f, "authentication failed (HTTP {}): {}", status, detail),
+            MistError::Auth {
+                status: Some(status),
+                detail: None,
+            } => write!(f, "authentication failed (HTTP {})", status),
+            MistError::Auth {
+                status: None,
+                detail: Some(detail),
+            } => write!(f, "authentication failed: {}", detail),
+            MistError::Auth {
+                status: None,
+                detail: None,
+            } => write!(f, "authentication failed"),
+            MistError::TwoFactorRequired => write!(f, "two-factor code required"),
+            MistError::Config(msg) => write!(f, "invalid configuration: {}", msg),
+            MistError::Invalid(msg) => write!(f, "invalid argument: {}", msg),
+            MistError::Io(e) => write!(f, "I/O error: {}", e),
+        }
+    }
+}
+
+impl Error for MistError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MistError::Transport(e) => Some(e),
+            MistError::Decode { error, .. } => Some(error),
+            MistError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for MistError {
+    fn from(e: reqwest::Error) -> Self {
+        MistError::Transport(e)
+    }
+}
+
+impl From<std::io::Error> for MistError {
+    fn from(e: std::io::Error) -> Self {
+        MistError::Io(e)
+    }
+}
+
+// private functions
+
+// `Retry-After` is either a number of seconds or an HTTP date
+fn retry_after_of(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    match value.parse::<u64>() {
+        Ok(secs) => Some(Duration::from_secs(secs)),
+        Err(_) => {
+            let at = httpdate::parse_http_date(value).ok()?;
+            Some(at.duration_since(SystemTime::now()).unwrap_or_default())
+        }
+    }
+}
+
+fn detail_of<'a>(body: &'a str) -> Option<String> {
+    match serde_json::from_str::<serde_json::Value>(body) {
+        Ok(serde_json::Value::Object(map)) => match map.get("detail") {
+            Some(serde_json::Value::String(detail)) => Some(detail.clone()),
+            Some(detail) => Some(detail.to_string()),
+            None => None,
+        },
+        _ => None,
+    }
+}
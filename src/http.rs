@@ -1,78 +1,505 @@
 // Copyright 2020 Eishun Kondoh
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::budget::{Budget, BudgetConfig};
+use crate::consts::{Cloud, MIST_API_BASE, MIST_API_PATH, MIST_USER_AGENT};
+use crate::error::MistError;
 use crate::logger;
-use reqwest::{blocking::Client, header, header::AUTHORIZATION};
-use serde::Serialize;
-use std::option_env;
+use crate::retry::RetryPolicy;
+use crate::session::Session;
+use reqwest::blocking::{Client, RequestBuilder};
+use reqwest::{header, header::HeaderMap, header::AUTHORIZATION, Proxy, StatusCode};
+use serde::{de::DeserializeOwned, Serialize};
+use std::env;
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+/// default `limit` of paginated requests
+pub const DEFAULT_PAGE_SIZE: u32 = 100;
 
 #[derive(Debug)]
 pub struct HttpClient {
     c: Client,
+    base: String,
+    page_size: u32,
+    retry: RetryPolicy,
+    budget: Budget,
+    // cookies of a password login, sent instead of reqwest's own store
+    session: Mutex<Session>,
+    token: bool,
+    // settings the client was built with, for clients of other tokens
+    config: HttpClientBuilder,
+}
+
+#[derive(Debug, Clone)]
+pub struct HttpClientBuilder {
+    token: Option<String>,
+    base: String,
+    timeout: Option<Duration>,
+    proxy: Option<String>,
+    user_agent: String,
+    page_size: u32,
+    retry: RetryPolicy,
+    budget: BudgetConfig,
+    session_file: Option<PathBuf>,
+}
+
+/// Lazy iterator over the pages of a list endpoint.
+///
+/// Pages are requested with `limit`/`page` one at a time, following
+/// the `X-Page-Total`/`X-Page-Limit` response headers, until a short or
+/// the last page is received.
+pub struct Pages<'a, U> {
+    c: &'a HttpClient,
+    url: String,
+    limit: u32,
+    page: u32,
+    done: bool,
+    item: PhantomData<U>,
 }
 
 impl HttpClient {
-    pub fn new() -> reqwest::Result<Self> {
-        let mut headers = header::HeaderMap::new();
-        headers.insert(AUTHORIZATION, api_token().unwrap());
-        let client = Client::builder()
-            .default_headers(headers)
-            .cookie_store(true)
-            .build()
-            .unwrap();
-        Ok(HttpClient { c: client })
+    /// Builds a client for the Global 01 cloud with the token read from
+    /// the `MIST_TOKEN` environment variable.
+    pub fn new() -> Result<Self, MistError> {
+        match env::var("MIST_TOKEN") {
+            Ok(token) => Self::builder().token(&token).build(),
+            Err(_) => {
+                logger::warn("env MIST_TOKEN is not configured");
+                Err(MistError::auth("env MIST_TOKEN is not configured"))
+            }
+        }
+    }
+
+    pub fn builder() -> HttpClientBuilder {
+        HttpClientBuilder::default()
+    }
+
+    /// A client with the host, proxy, timeout, user agent, page size,
+    /// retry and budget settings of this one, authenticated with `token`
+    /// instead of the token or session of this one
+    pub fn with_token<'a>(&self, token: &'a str) -> Result<Self, MistError> {
+        self.config.clone().token(token).build()
+    }
+
+    /// Base URL of the API, e.g. `https://api.eu.mist.com/api/v1`
+    pub fn base_url(&self) -> &str {
+        &self.base
+    }
+
+    /// Absolute URL of an API path relative to the base URL
+    pub fn url<'a>(&self, path: &'a str) -> String {
+        format!("{}/{}", self.base, path)
+    }
+
+    pub fn page_size(&self) -> u32 {
+        self.page_size
+    }
+
+    /// Requests sent with the token of this client over the last hour
+    pub fn budget(&self) -> &Budget {
+        &self.budget
+    }
+
+    /// Iterates over the pages of a list endpoint with the page size of
+    /// the client. `url` may already carry a query string.
+    pub fn paginate<U>(&self, url: String) -> Pages<'_, U>
+    where
+        U: DeserializeOwned,
+    {
+        Pages {
+            c: self,
+            url,
+            limit: self.page_size,
+            page: 1,
+            done: false,
+            item: PhantomData,
+        }
+    }
+
+    /// Follows every page of a list endpoint and collects the items
+    pub fn get_all<U>(&self, url: String) -> Result<Vec<U>, MistError>
+    where
+        U: DeserializeOwned,
+    {
+        let mut items = Vec::new();
+        for page in self.paginate(url) {
+            items.extend(page?);
+        }
+        Ok(items)
+    }
+
+    pub fn get<T, U>(&self, url: String, body: &T) -> Result<U, MistError>
+    where
+        U: DeserializeOwned,
+        T: Serialize + ?Sized,
+    {
+        self.send(self.c.get(&*url).json(body), true)
     }
 
-    pub fn get<T, U>(&self, url: String, body: &T) -> reqwest::Result<Option<U>>
+    pub fn post<T, U>(&self, url: String, body: &T) -> Result<U, MistError>
     where
-        U: for<'de> serde::Deserialize<'de>,
+        U: DeserializeOwned,
         T: Serialize + ?Sized,
     {
-        let request = self.c.get(&*url).json(body);
-        let response: U = request.send()?.json()?;
-        Ok(Some(response))
+        self.send(self.c.post(&*url).json(body), self.retry.retry_post)
     }
 
-    pub fn post<T, U>(&self, url: String, body: &T) -> reqwest::Result<Option<U>>
+    /// POST which is retried like GET/PUT/DELETE, for requests the
+    /// caller knows are safe to send twice
+    pub fn post_idempotent<T, U>(&self, url: String, body: &T) -> Result<U, MistError>
     where
-        U: for<'de> serde::Deserialize<'de>,
+        U: DeserializeOwned,
         T: Serialize + ?Sized,
     {
-        let request = self.c.post(&*url).json(body);
-        let response: U = request.send()?.json()?;
-        Ok(Some(response))
+        self.send(self.c.post(&*url).json(body), true)
     }
 
-    pub fn put<T, U>(&self, url: String, body: &T) -> reqwest::Result<Option<U>>
+    pub fn put<T, U>(&self, url: String, body: &T) -> Result<U, MistError>
     where
-        U: for<'de> serde::Deserialize<'de>,
+        U: DeserializeOwned,
         T: Serialize + ?Sized,
     {
-        let request = self.c.put(&*url).json(body);
-        let response: U = request.send()?.json()?;
-        Ok(Some(response))
+        self.send(self.c.put(&*url).json(body), true)
     }
 
-    pub fn delete<T>(&self, url: String, body: &T) -> reqwest::Result<Option<serde_json::Value>>
+    pub fn delete<T>(&self, url: String, body: &T) -> Result<serde_json::Value, MistError>
     where
         T: Serialize + ?Sized,
     {
-        let request = self.c.delete(&*url).json(body);
-        let response: serde_json::Value = request.send()?.json()?;
-        Ok(Some(response))
+        self.send(self.c.delete(&*url).json(body), true)
+    }
+
+    /// Whether the client holds an unexpired login session cookie
+    pub fn is_logged_in(&self) -> bool {
+        self.session().is_logged_in()
+    }
+
+    /// Epoch seconds at which the login session expires, if known
+    pub fn session_expires_at(&self) -> Option<u64> {
+        self.session().expires_at()
+    }
+
+    /// Forgets the login session and removes its file
+    pub fn clear_session(&self) -> Result<(), MistError> {
+        self.session().clear()
+    }
+
+    fn session(&self) -> std::sync::MutexGuard<'_, Session> {
+        // a panic while holding the lock leaves the jar usable
+        self.session.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn send<U>(&self, request: RequestBuilder, retry: bool) -> Result<U, MistError>
+    where
+        U: DeserializeOwned,
+    {
+        let (_, body) = self.execute(request, retry)?;
+        decode(body)
+    }
+
+    /// Sends a request, retrying it according to the retry policy when
+    /// `retry` is set, and returns the headers and body of a 2xx response
+    fn execute(
+        &self,
+        mut request: RequestBuilder,
+        retry: bool,
+    ) -> Result<(HeaderMap, String), MistError> {
+        let mut attempt = 1;
+        loop {
+            let again = if retry { request.try_clone() } else { None };
+            self.budget.acquire()?;
+            let e = match self.execute_once(request) {
+                Err(e) if e.is_retryable() => e,
+                result => return result,
+            };
+            let delay = match (again, self.retry.delay(attempt, e.retry_after())) {
+                (Some(again), Some(delay)) => {
+                    request = again;
+                    delay
+                }
+                _ => return Err(e),
+            };
+            logger::warn(&format!(
+                "{}, retrying in {:?} (attempt {}/{})",
+                e,
+                delay,
+                attempt + 1,
+                self.retry.max_attempts
+            ));
+            thread::sleep(delay);
+            attempt += 1;
+        }
+    }
+
+    /// Sends a request once with the session cookies and CSRF header,
+    /// recording the cookies set by the response
+    fn execute_once(&self, request: RequestBuilder) -> Result<(HeaderMap, String), MistError> {
+        let mut request = request.build()?;
+        self.session().apply(&mut request);
+        let response = self.c.execute(request)?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        self.session().store(&headers)?;
+        let body = response.text()?;
+        if status == StatusCode::UNAUTHORIZED && !self.token && self.is_logged_in() {
+            // the server ended the session before the cookie expired
+            self.clear_session()?;
+            return Err(MistError::auth(
+                "login session expired, run `mist login` again",
+            ));
+        }
+        if !status.is_success() {
+            return Err(MistError::from_response(status, &headers, body));
+        }
+        Ok((headers, body))
     }
 }
 
-fn api_token() -> Result<header::HeaderValue, ()> {
-    match option_env!("MIST_TOKEN") {
-        None => {
-            logger::warn("env MIST_TOKEN is not configured");
-            Err(())
+impl HttpClientBuilder {
+    /// API token sent as `Authorization: Token <token>`.
+    /// Without a token the client relies on the session cookie of `login`.
+    pub fn token<'a>(mut self, token: &'a str) -> Self {
+        self.token = Some(token.to_string());
+        self
+    }
+
+    pub fn cloud(mut self, cloud: Cloud) -> Self {
+        self.base = cloud.api_base();
+        self
+    }
+
+    /// Arbitrary API host, with or without scheme and `/api/v1`,
+    /// e.g. `api.eu.mist.com` or `https://api.eu.mist.com/api/v1`
+    pub fn base_url<'a>(mut self, url: &'a str) -> Self {
+        self.base = normalize_base_url(url);
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn proxy<'a>(mut self, proxy: &'a str) -> Self {
+        self.proxy = Some(proxy.to_string());
+        self
+    }
+
+    pub fn user_agent<'a>(mut self, user_agent: &'a str) -> Self {
+        self.user_agent = user_agent.to_string();
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn budget(mut self, budget: BudgetConfig) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// File keeping the cookies of a password login between processes
+    pub fn session_file(mut self, path: PathBuf) -> Self {
+        self.session_file = Some(path);
+        self
+    }
+
+    /// `limit` of paginated requests, Mist accepts up to 1000
+    pub fn page_size(mut self, page_size: u32) -> Self {
+        self.page_size = page_size.clamp(1, 1000);
+        self
+    }
+
+    pub fn build(self) -> Result<HttpClient, MistError> {
+        let config = HttpClientBuilder {
+            token: None,
+            session_file: None,
+            ..self.clone()
+        };
+        let mut headers = header::HeaderMap::new();
+        if let Some(token) = &self.token {
+            headers.insert(AUTHORIZATION, api_token(token)?);
+        }
+        let mut builder = Client::builder()
+            .default_headers(headers)
+            .user_agent(&*self.user_agent);
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
         }
-        Some(token) => {
-            let token = &*format!("Token {}", token);
-            let header = header::HeaderValue::from_str(token).unwrap();
-            Ok(header)
+        if let Some(proxy) = &self.proxy {
+            let proxy = Proxy::all(&**proxy)
+                .map_err(|e| MistError::Config(format!("invalid proxy {}: {}", proxy, e)))?;
+            builder = builder.proxy(proxy);
         }
+        let session = match self.session_file {
+            Some(path) => Session::load(&self.base, path),
+            None => Session::new(&self.base),
+        };
+        Ok(HttpClient {
+            c: builder.build()?,
+            base: self.base,
+            page_size: self.page_size,
+            retry: self.retry,
+            budget: Budget::new(self.token.as_deref(), self.budget),
+            session: Mutex::new(session),
+            token: self.token.is_some(),
+            config,
+        })
+    }
+}
+
+impl Default for HttpClientBuilder {
+    fn default() -> Self {
+        HttpClientBuilder {
+            token: None,
+            base: MIST_API_BASE.to_string(),
+            timeout: None,
+            proxy: None,
+            user_agent: MIST_USER_AGENT.to_string(),
+            page_size: DEFAULT_PAGE_SIZE,
+            retry: RetryPolicy::default(),
+            budget: BudgetConfig::default(),
+            session_file: None,
+        }
+    }
+}
+
+impl<'a, U> Iterator for Pages<'a, U>
+where
+    U: DeserializeOwned,
+{
+    type Item = Result<Vec<U>, MistError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let url = page_url(&self.url, self.limit, self.page);
+        let (headers, items) = match self.c.execute(self.c.c.get(&*url), true) {
+            Ok((headers, body)) => match decode::<Vec<U>>(body) {
+                Ok(items) => (headers, items),
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            },
+            Err(e) => {
+                self.done = true;
+                return Some(Err(e));
+            }
+        };
+        let limit = page_header(&headers, "X-Page-Limit").unwrap_or(self.limit);
+        let total = page_header(&headers, "X-Page-Total");
+        self.done = is_last_page(self.page, limit, items.len(), total);
+        // an empty page after a full one only means the listing ended
+        if items.is_empty() && self.page > 1 {
+            return None;
+        }
+        self.page += 1;
+        Some(Ok(items))
+    }
+}
+
+/// Whether page `page` of `limit` items, which had `fetched` items, ends
+/// a listing of `total` items. `limit` is the one the server applied,
+/// which may be lower than the one requested.
+pub(crate) fn is_last_page(page: u32, limit: u32, fetched: usize, total: Option<u32>) -> bool {
+    let seen = u64::from(page) * u64::from(limit);
+    fetched == 0 || fetched < limit as usize || total.is_some_and(|total| seen >= u64::from(total))
+}
+
+// private functions
+
+fn decode<U>(body: String) -> Result<U, MistError>
+where
+    U: DeserializeOwned,
+{
+    // DELETE and some PUT endpoints answer with an empty body
+    let json = if body.trim().is_empty() {
+        "null"
+    } else {
+        &body
+    };
+    serde_json::from_str(json).map_err(|error| MistError::Decode { error, body })
+}
+
+fn page_url<'a>(url: &'a str, limit: u32, page: u32) -> String {
+    let sep = if url.contains('?') { '&' } else { '?' };
+    format!("{}{}limit={}&page={}", url, sep, limit, page)
+}
+
+fn page_header<'a>(headers: &HeaderMap, name: &'a str) -> Option<u32> {
+    headers.get(name)?.to_str().ok()?.trim().parse().ok()
+}
+
+fn api_token<'a>(token: &'a str) -> Result<header::HeaderValue, MistError> {
+    let token = &*format!("Token {}", token);
+    header::HeaderValue::from_str(token)
+        .map_err(|_| MistError::Config("API token contains invalid characters".to_string()))
+}
+
+fn normalize_base_url<'a>(url: &'a str) -> String {
+    let url = url.trim().trim_end_matches('/');
+    let url = if url.contains("://") {
+        url.to_string()
+    } else {
+        format!("https://{}", url)
+    };
+    if url.ends_with(MIST_API_PATH) {
+        url
+    } else {
+        format!("{}/{}", url, MIST_API_PATH)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_url_appends_paging() {
+        assert_eq!(
+            page_url("https://api.mist.com/api/v1/orgs/x/sites", 100, 1),
+            "https://api.mist.com/api/v1/orgs/x/sites?limit=100&page=1"
+        );
+        assert_eq!(
+            page_url(
+                "https://api.mist.com/api/v1/orgs/x/inventory?type=ap",
+                50,
+                3
+            ),
+            "https://api.mist.com/api/v1/orgs/x/inventory?type=ap&limit=50&page=3"
+        );
+    }
+
+    #[test]
+    fn short_or_empty_page_is_last() {
+        assert!(!is_last_page(1, 100, 100, None));
+        assert!(is_last_page(1, 100, 99, None));
+        assert!(is_last_page(2, 100, 0, None));
+        // a limit of 0 must not keep the listing going
+        assert!(is_last_page(1, 0, 0, None));
+    }
+
+    #[test]
+    fn total_ends_a_listing_of_full_pages() {
+        assert!(!is_last_page(1, 100, 100, Some(250)));
+        assert!(!is_last_page(2, 100, 100, Some(250)));
+        assert!(is_last_page(2, 100, 100, Some(200)));
+        assert!(is_last_page(3, 100, 100, Some(250)));
+    }
+
+    #[test]
+    fn server_limit_below_the_requested_one() {
+        // 1000 requested, the server applies 100: full pages of 100
+        // continue until the total is reached
+        assert!(!is_last_page(1, 100, 100, Some(150)));
+        assert!(is_last_page(2, 100, 50, Some(150)));
     }
 }
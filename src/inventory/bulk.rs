@@ -0,0 +1,482 @@
+// Copyright 2020 Eishun Kondoh
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::error::MistError;
+use crate::http::HttpClient;
+use crate::inventory::inventory::{self, ClaimDeviceReply, ClaimedDevice};
+use crate::logger::{debug, info, warn};
+use crate::output::Render;
+use crate::types::{DeviceId, MacAddr, OrgId, SiteId};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// A row of a bulk claim file:
+///
+/// ```text
+/// claim_code,site,name,deviceprofile
+/// ABCDEFGHIJKLMNO,Tokyo,ap-tokyo-01,
+/// ```
+///
+/// `site` is a site ID or name, `deviceprofile` a device profile ID. Only
+/// `claim_code` is required.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ClaimRow {
+    pub claim_code: String,
+    #[serde(default)]
+    pub site: Option<String>,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub deviceprofile: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ClaimResults(Vec<ClaimResult>);
+
+/// Outcome of a row of the claim file
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ClaimResult {
+    pub claim_code: String,
+    pub status: ClaimStatus,
+    #[serde(default)]
+    pub mac: Option<MacAddr>,
+    #[serde(default)]
+    pub serial: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub site_id: Option<SiteId>,
+    #[serde(default)]
+    pub name: Option<String>,
+    // why the claim, assignment or naming failed
+    #[serde(default)]
+    pub detail: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ClaimStatus {
+    Added,
+    Duplicated,
+    // the code was not claimed
+    Error,
+    // claimed, but not assigned to the site of the row
+    AssignFailed,
+    // claimed and assigned, but the name or device profile was not set
+    ConfigureFailed,
+}
+
+// name and device profile set on a claimed device
+#[derive(Serialize, Debug)]
+struct DeviceUpdate<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    deviceprofile_id: Option<&'a str>,
+}
+
+impl ClaimStatus {
+    pub fn is_failure(self) -> bool {
+        !matches!(self, ClaimStatus::Added | ClaimStatus::Duplicated)
+    }
+}
+
+impl ClaimResults {
+    pub fn count(&self, status: ClaimStatus) -> usize {
+        self.0.iter().filter(|r| r.status == status).count()
+    }
+
+    /// rows which were not claimed, assigned or configured as asked
+    pub fn failed(&self) -> usize {
+        self.0.iter().filter(|r| r.status.is_failure()).count()
+    }
+}
+
+impl From<Vec<ClaimResult>> for ClaimResults {
+    fn from(results: Vec<ClaimResult>) -> Self {
+        ClaimResults(results)
+    }
+}
+
+impl Render for ClaimResults {
+    type Record = ClaimResult;
+
+    fn default_columns() -> &'static [&'static str] {
+        &[
+            "claim_code",
+            "status",
+            "mac",
+            "serial",
+            "model",
+            "site_id",
+            "name",
+            "detail",
+        ]
+    }
+
+    fn records(&self) -> Vec<&ClaimResult> {
+        self.0.iter().collect()
+    }
+}
+
+/// Reads the rows of a claim file, see `ClaimRow`
+pub fn read_claims<'a>(path: &'a Path) -> Result<Vec<ClaimRow>, MistError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_path(path)
+        .map_err(|e| MistError::Invalid(format!("{}: {}", path.display(), e)))?;
+    let mut rows = Vec::new();
+    for row in reader.deserialize::<ClaimRow>() {
+        let row = row.map_err(|e| MistError::Invalid(format!("{}: {}", path.display(), e)))?;
+        if !row.claim_code.is_empty() {
+            rows.push(row);
+        }
+    }
+    Ok(rows)
+}
+
+/// Claims the devices of `rows` in chunks of `chunk_size` codes, then
+/// assigns each added device to the site of its row and sets its name
+/// and device profile.
+///
+/// The sites of every row are resolved with `site_id` before anything is
+/// claimed. Failures after that are recorded in the result of the rows
+/// they concern, so one bad code or chunk does not stop the others.
+pub fn claim_rows<'a, F>(
+    c: &HttpClient,
+    org_id: &'a OrgId,
+    rows: &'a [ClaimRow],
+    chunk_size: usize,
+    mut site_id: F,
+) -> Result<ClaimResults, MistError>
+where
+    F: FnMut(&str) -> Result<SiteId, MistError>,
+{
+    let mut site_ids = Vec::with_capacity(rows.len());
+    for (i, row) in rows.iter().enumerate() {
+        let id = match row.site.as_deref().filter(|site| !site.is_empty()) {
+            // the header is line 1
+            Some(site) => Some(site_id(site).map_err(|e| {
+                MistError::Invalid(format!("line {}: {}: {}", i + 2, row.claim_code, e))
+            })?),
+            None => None,
+        };
+        site_ids.push(id);
+    }
+
+    let claimed = claim_codes(c, org_id, rows, chunk_size.max(1));
+    let mut results: Vec<ClaimResult> = rows
+        .iter()
+        .zip(site_ids)
+        .map(|(row, site_id)| result_of(row, site_id, &claimed))
+        .collect();
+
+    assign_sites(c, org_id, &mut results);
+    for (result, row) in results.iter_mut().zip(rows) {
+        configure(c, result, row);
+    }
+    let results = ClaimResults(results);
+    info(&format!(
+        "{} added, {} duplicated, {} failed, {} not assigned, {} not configured",
+        results.count(ClaimStatus::Added),
+        results.count(ClaimStatus::Duplicated),
+        results.count(ClaimStatus::Error),
+        results.count(ClaimStatus::AssignFailed),
+        results.count(ClaimStatus::ConfigureFailed)
+    ));
+    Ok(results)
+}
+
+// private functions
+
+enum Claimed {
+    Done(ClaimStatus, Option<ClaimedDevice>),
+    Failed(String),
+}
+
+// claim codes are matched regardless of case and dashes
+fn code_key<'a>(code: &'a str) -> String {
+    code.chars()
+        .filter(|ch| *ch != '-')
+        .map(|ch| ch.to_ascii_uppercase())
+        .collect()
+}
+
+fn claim_codes<'a>(
+    c: &HttpClient,
+    org_id: &'a OrgId,
+    rows: &'a [ClaimRow],
+    chunk_size: usize,
+) -> HashMap<String, Claimed> {
+    let mut claimed = HashMap::new();
+    for chunk in unique_codes(rows).chunks(chunk_size) {
+        match inventory::claim(c, org_id, chunk.to_vec()) {
+            Ok(reply) => {
+                debug(&format!(
+                    "claimed {} codes: {} added, {} duplicated, {} failed",
+                    chunk.len(),
+                    reply.added.len(),
+                    reply.duplicated.len(),
+                    reply.error.len()
+                ));
+                record_reply(&mut claimed, reply);
+            }
+            Err(e) => {
+                for code in chunk {
+                    claimed.insert(code_key(code), Claimed::Failed(e.to_string()));
+                }
+            }
+        }
+    }
+    claimed
+}
+
+// codes of the rows, each once
+fn unique_codes<'a>(rows: &'a [ClaimRow]) -> Vec<&'a str> {
+    let mut codes: Vec<&str> = Vec::new();
+    for row in rows {
+        if !codes
+            .iter()
+            .any(|code| code_key(code) == code_key(&row.claim_code))
+        {
+            codes.push(&row.claim_code);
+        }
+    }
+    codes
+}
+
+// outcome of each code of a claim reply, with the device it claimed
+fn record_reply(claimed: &mut HashMap<String, Claimed>, reply: ClaimDeviceReply) {
+    let devices: HashMap<String, ClaimedDevice> = reply
+        .inventory_added
+        .into_iter()
+        .chain(reply.inventory_duplicated)
+        .filter_map(|device| Some((code_key(device.magic.as_deref()?), device)))
+        .collect();
+    let outcomes = [
+        (reply.added, ClaimStatus::Added),
+        (reply.duplicated, ClaimStatus::Duplicated),
+    ];
+    for (codes, status) in outcomes {
+        for code in codes {
+            let key = code_key(&code);
+            let device = devices.get(&key).cloned();
+            claimed.insert(key, Claimed::Done(status, device));
+        }
+    }
+    for code in reply.error {
+        let detail = "claim code invalid or claimed by another org".to_string();
+        claimed.insert(code_key(&code), Claimed::Failed(detail));
+    }
+}
+
+// result of a row from the outcome of its code
+fn result_of<'a>(
+    row: &'a ClaimRow,
+    site_id: Option<SiteId>,
+    claimed: &'a HashMap<String, Claimed>,
+) -> ClaimResult {
+    let (status, device, detail) = match claimed.get(&code_key(&row.claim_code)) {
+        Some(Claimed::Done(status, device)) => (*status, device.clone(), None),
+        Some(Claimed::Failed(detail)) => (ClaimStatus::Error, None, Some(detail.clone())),
+        None => (
+            ClaimStatus::Error,
+            None,
+            Some("missing from the claim reply".to_string()),
+        ),
+    };
+    ClaimResult {
+        claim_code: row.claim_code.clone(),
+        status,
+        mac: device.as_ref().map(|d| d.mac.clone()),
+        serial: device.as_ref().and_then(|d| d.serial.clone()),
+        model: device.as_ref().and_then(|d| d.model.clone()),
+        site_id,
+        name: row.name.clone(),
+        detail,
+    }
+}
+
+// assigns the added devices, one request per site
+fn assign_sites<'a>(c: &HttpClient, org_id: &'a OrgId, results: &'a mut [ClaimResult]) {
+    let mut by_site: BTreeMap<SiteId, Vec<MacAddr>> = BTreeMap::new();
+    for result in results
+        .iter_mut()
+        .filter(|r| r.status == ClaimStatus::Added)
+    {
+        match (&result.site_id, &result.mac) {
+            (Some(site_id), Some(mac)) => {
+                let macs = by_site.entry(site_id.clone()).or_default();
+                if !macs.contains(mac) {
+                    macs.push(mac.clone());
+                }
+            }
+            // added, but without the MAC the device cannot be assigned
+            (Some(_), None) => {
+                warn(&format!(
+                    "{}: device not in claim reply, not assigned",
+                    result.claim_code
+                ));
+                result.status = ClaimStatus::AssignFailed;
+                result.detail = Some("device not in claim reply, not assigned".to_string());
+            }
+            (None, _) => {}
+        }
+    }
+    for (site_id, macs) in by_site {
+        let failed: Vec<(MacAddr, String)> =
+            match inventory::assign(c, org_id, &site_id, macs.clone(), None, None, None) {
+                Ok(reply) => macs
+                    .into_iter()
+                    .filter(|mac| reply.error.iter().any(|e| e == mac.as_str()))
+                    .map(|mac| (mac, "assign to site failed".to_string()))
+                    .collect(),
+                Err(e) => macs
+                    .into_iter()
+                    .map(|mac| (mac, format!("assign to site failed: {}", e)))
+                    .collect(),
+            };
+        for (mac, detail) in failed {
+            warn(&format!("{}: {}", mac, detail));
+            for result in results.iter_mut() {
+                if result.mac.as_ref() == Some(&mac) && result.site_id.as_ref() == Some(&site_id) {
+                    result.status = ClaimStatus::AssignFailed;
+                    result.detail = Some(detail.clone());
+                }
+            }
+        }
+    }
+}
+
+// names an assigned device and sets its device profile
+fn configure<'a>(c: &HttpClient, result: &'a mut ClaimResult, row: &'a ClaimRow) {
+    let update = DeviceUpdate {
+        name: row.name.as_deref().filter(|name| !name.is_empty()),
+        deviceprofile_id: row.deviceprofile.as_deref().filter(|id| !id.is_empty()),
+    };
+    if result.status != ClaimStatus::Added
+        || result.detail.is_some()
+        || (update.name.is_none() && update.deviceprofile_id.is_none())
+    {
+        return;
+    }
+    let (site_id, mac) = match (&result.site_id, &result.mac) {
+        (Some(site_id), Some(mac)) => (site_id, mac),
+        _ => {
+            result.status = ClaimStatus::ConfigureFailed;
+            result.detail = Some("name and device profile need a site".to_string());
+            return;
+        }
+    };
+    let url = c.url(&format!(
+        "sites/{}/devices/{}",
+        site_id,
+        DeviceId::from_mac(mac)
+    ));
+    match c.put::<_, serde_json::Value>(url, &update) {
+        Ok(_) => debug(&format!("update device {} request succeed", mac)),
+        Err(e) => {
+            warn(&format!("update device {} request failed: {}", mac, e));
+            result.status = ClaimStatus::ConfigureFailed;
+            result.detail = Some(format!("update device failed: {}", e));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(code: &str) -> ClaimRow {
+        ClaimRow {
+            claim_code: code.to_string(),
+            site: None,
+            name: None,
+            deviceprofile: None,
+        }
+    }
+
+    #[test]
+    fn code_key_ignores_case_and_dashes() {
+        assert_eq!(code_key("abcde-fghij-klmno"), "ABCDEFGHIJKLMNO");
+        assert_eq!(code_key("ABCDEFGHIJKLMNO"), "ABCDEFGHIJKLMNO");
+        assert_ne!(code_key("ABCDE"), code_key("ABCDF"));
+    }
+
+    #[test]
+    fn unique_codes_keep_the_first_spelling() {
+        let rows = [
+            row("abcde-fghij"),
+            row("KLMNOPQRST"),
+            row("ABCDEFGHIJ"),
+            row("klmno-pqrst"),
+        ];
+        assert_eq!(unique_codes(&rows), vec!["abcde-fghij", "KLMNOPQRST"]);
+    }
+
+    #[test]
+    fn reply_maps_back_to_rows() {
+        let reply: ClaimDeviceReply = serde_json::from_value(serde_json::json!({
+            "added": ["AAAAA"],
+            "duplicated": ["BBBBB"],
+            "error": ["CCCCC"],
+            "inventory_added": [{"magic": "AAAAA", "mac": "5c5b35000001", "serial": "S1"}],
+            "inventory_duplicated": [{"magic": "BBBBB", "mac": "5c5b35000002"}],
+        }))
+        .unwrap();
+        let mut claimed = HashMap::new();
+        record_reply(&mut claimed, reply);
+
+        let added = result_of(&row("aaa-aa"), None, &claimed);
+        assert_eq!(added.status, ClaimStatus::Added);
+        assert_eq!(added.mac.unwrap().as_str(), "5c5b35000001");
+        assert_eq!(added.serial.as_deref(), Some("S1"));
+        assert_eq!(added.claim_code, "aaa-aa");
+
+        let duplicated = result_of(&row("BBBBB"), None, &claimed);
+        assert_eq!(duplicated.status, ClaimStatus::Duplicated);
+        assert_eq!(duplicated.mac.unwrap().as_str(), "5c5b35000002");
+
+        let error = result_of(&row("CCCCC"), None, &claimed);
+        assert_eq!(error.status, ClaimStatus::Error);
+        assert!(error.mac.is_none());
+        assert!(error.detail.is_some());
+
+        let missing = result_of(&row("DDDDD"), None, &claimed);
+        assert_eq!(missing.status, ClaimStatus::Error);
+        assert_eq!(
+            missing.detail.as_deref(),
+            Some("missing from the claim reply")
+        );
+    }
+
+    #[test]
+    fn added_without_device_has_no_mac() {
+        let reply: ClaimDeviceReply =
+            serde_json::from_value(serde_json::json!({"added": ["AAAAA"]})).unwrap();
+        let mut claimed = HashMap::new();
+        record_reply(&mut claimed, reply);
+        let added = result_of(&row("AAAAA"), None, &claimed);
+        assert_eq!(added.status, ClaimStatus::Added);
+        assert!(added.mac.is_none());
+    }
+
+    #[test]
+    fn failures_are_counted() {
+        let result = |status| ClaimResult {
+            status,
+            ..result_of(&row("AAAAA"), None, &HashMap::new())
+        };
+        let results: ClaimResults = vec![
+            result(ClaimStatus::Added),
+            result(ClaimStatus::Duplicated),
+            result(ClaimStatus::Error),
+            result(ClaimStatus::AssignFailed),
+            result(ClaimStatus::ConfigureFailed),
+        ]
+        .into();
+        assert_eq!(results.failed(), 3);
+        assert_eq!(results.count(ClaimStatus::Added), 1);
+    }
+}
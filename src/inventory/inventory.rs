@@ -1,10 +1,14 @@
 // Copyright 2020 Eishun Kondoh
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::consts::MIST_API_BASE;
-use crate::http::HttpClient;
-use crate::logger::{debug, warn};
+use crate::auth::login;
+use crate::error::MistError;
+use crate::http::{self, HttpClient, Pages};
+use crate::logger::{debug, info, warn};
+use crate::output::Render;
+use crate::types::{DeviceId, DeviceType, MacAddr, OrgId, Scope, SiteId};
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 
 ///
 /// ref: https://api.mist.com/api/v1/docs/Org#inventory
@@ -18,20 +22,22 @@ pub struct Inventory {
     // device serial
     pub serial: String,
     // device id
-    pub id: String,
+    pub id: DeviceId,
     // device model
     pub model: String,
     // device type, ap
     #[serde(rename = "type")]
-    pub ty: String,
+    pub ty: DeviceType,
     // MAC address
-    pub mac: String,
+    pub mac: MacAddr,
     // device name if assigned
     #[serde(default)]
     pub name: Option<String>,
+    #[serde(default)]
+    pub org_id: Option<OrgId>,
     // site id if assigned, null if not assigned
     #[serde(default)]
-    pub site_id: Option<String>,
+    pub site_id: Option<SiteId>,
     // deviceprofile id if assigned, null if not assigned
     #[serde(default)]
     pub deviceprofile_id: Option<String>,
@@ -61,27 +67,42 @@ pub struct ClaimDeviceReply {
     #[serde(default)]
     pub error: Vec<String>,
     #[serde(default)]
-    pub inventory_added: Inventories,
+    pub inventory_added: Vec<ClaimedDevice>,
     #[serde(default)]
-    pub inventory_duplicated: Inventories,
+    pub inventory_duplicated: Vec<ClaimedDevice>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+/// inventory entry of a claimed device, only the fields known at claim
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ClaimedDevice {
+    pub mac: MacAddr,
+    // claim code
+    #[serde(default)]
+    pub magic: Option<String>,
+    #[serde(default)]
+    pub serial: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(rename = "type", default)]
+    pub ty: Option<DeviceType>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
 pub struct DelInventory {
     #[serde(default = "delete_op")]
     pub op: String,
     #[serde(default)]
     pub serials: Vec<String>,
     #[serde(default)]
-    pub macs: Vec<String>,
+    pub macs: Vec<MacAddr>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct AssignInventory {
     #[serde(default = "assign_op")]
     pub op: String,
-    pub site_id: String,
-    pub macs: Vec<String>,
+    pub site_id: SiteId,
+    pub macs: Vec<MacAddr>,
     #[serde(default)]
     pub no_reassign: Option<bool>,
     #[serde(default)]
@@ -90,131 +111,576 @@ pub struct AssignInventory {
     pub managed: Option<bool>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct UnassignInventory {
     #[serde(default = "unassign_op")]
     pub op: String,
-    pub macs: Vec<String>,
+    pub macs: Vec<MacAddr>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UpgradeInventory {
+    #[serde(default = "upgrade_to_mist_op")]
+    pub op: String,
+    pub macs: Vec<MacAddr>,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ReplaceInventory {
+    // device to be replaced
+    pub mac: MacAddr,
+    // unassigned device of the inventory taking over its config
+    pub inventory_mac: MacAddr,
+    pub site_id: SiteId,
+    // attributes not copied over, e.g. name, notes, deviceprofile_id
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub discard: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct InventoryReplies(Vec<InventoryReply>);
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct InventoryReply {
+    #[serde(default)]
     pub op: String,
     pub success: Vec<String>,
     pub error: Vec<String>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct InventoryCounts {
+    #[serde(default)]
+    pub distinct: Option<String>,
+    #[serde(default)]
+    pub total: Option<u32>,
+    // groups per page, as applied by the server
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    #[serde(default)]
+    pub results: Vec<InventoryCount>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct InventoryCount {
+    // only the field counted by is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub site_id: Option<SiteId>,
+    pub count: u32,
+}
+
+/// field `count` groups devices by
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CountBy {
+    Model,
+    SiteId,
+}
+
+/// Filters of `list`, e.g.
+/// `InventoryQuery::new().ty(DeviceType::Ap).unassigned(true)`
+#[derive(Serialize, Debug, Default, Clone)]
+pub struct InventoryQuery {
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    ty: Option<DeviceType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    serial: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mac: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    site_id: Option<SiteId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unassigned: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    vc: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    modified_after: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limit: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page: Option<u32>,
+}
+
+impl CountBy {
+    /// column of the count results
+    pub fn column(&self) -> &'static str {
+        match self {
+            CountBy::Model => "model",
+            CountBy::SiteId => "site_id",
+        }
+    }
+}
+
+impl FromStr for CountBy {
+    type Err = MistError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match &*s.to_ascii_lowercase() {
+            "model" => Ok(CountBy::Model),
+            "site" | "site_id" => Ok(CountBy::SiteId),
+            _ => Err(MistError::Invalid(format!(
+                "unknown count field: {}, expected model or site",
+                s
+            ))),
+        }
+    }
+}
+
+impl InventoryQuery {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn ty(mut self, ty: DeviceType) -> Self {
+        self.ty = Some(ty);
+        self
+    }
+
+    pub fn model<'a>(mut self, model: &'a str) -> Self {
+        self.model = Some(model.to_string());
+        self
+    }
+
+    pub fn serial<'a>(mut self, serial: &'a str) -> Self {
+        self.serial = Some(serial.to_string());
+        self
+    }
+
+    /// any of `5c5b35aabbcc`, `5c:5b:35:aa:bb:cc` or `5c-5b-35-aa-bb-cc`
+    pub fn mac<'a>(mut self, mac: &'a str) -> Self {
+        self.mac = Some(mac.to_string());
+        self
+    }
+
+    pub fn site_id<'a>(mut self, site_id: &'a SiteId) -> Self {
+        self.site_id = Some(site_id.clone());
+        self
+    }
+
+    pub fn unassigned(mut self, unassigned: bool) -> Self {
+        self.unassigned = Some(unassigned);
+        self
+    }
+
+    /// include the members of virtual chassis
+    pub fn vc(mut self, vc: bool) -> Self {
+        self.vc = Some(vc);
+        self
+    }
+
+    /// epoch seconds
+    pub fn modified_after(mut self, epoch: u64) -> Self {
+        self.modified_after = Some(epoch);
+        self
+    }
+
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn page(mut self, page: u32) -> Self {
+        self.page = Some(page);
+        self
+    }
+
+    /// Validates the filters and encodes them as a query string
+    pub fn to_query_string(&self) -> Result<String, MistError> {
+        let mut query = self.clone();
+        if let Some(mac) = &self.mac {
+            query.mac = Some(mac.parse::<MacAddr>()?.to_string());
+        }
+        if let Some(serial) = &self.serial {
+            validate_serial(serial)?;
+        }
+        serde_urlencoded::to_string(&query).map_err(|e| MistError::Invalid(e.to_string()))
+    }
+
+    // paginated listings set limit and page themselves
+    fn without_paging(&self) -> Self {
+        InventoryQuery {
+            limit: None,
+            page: None,
+            ..self.clone()
+        }
+    }
+}
+
+impl Inventories {
+    pub fn iter(&self) -> impl Iterator<Item = &Inventory> {
+        self.0.iter()
+    }
+}
+
+impl From<Vec<Inventory>> for Inventories {
+    fn from(v: Vec<Inventory>) -> Self {
+        Inventories(v)
+    }
+}
+
+impl Render for Inventories {
+    type Record = Inventory;
+
+    fn default_columns() -> &'static [&'static str] {
+        &[
+            "mac",
+            "serial",
+            "model",
+            "type",
+            "name",
+            "site_id",
+            "connected",
+        ]
+    }
+
+    fn records(&self) -> Vec<&Inventory> {
+        self.0.iter().collect()
+    }
+}
+
+impl Render for ClaimDeviceReply {
+    type Record = ClaimDeviceReply;
+
+    fn default_columns() -> &'static [&'static str] {
+        &["added", "duplicated", "error"]
+    }
+
+    fn records(&self) -> Vec<&ClaimDeviceReply> {
+        vec![self]
+    }
+}
+
+impl Render for InventoryCounts {
+    type Record = InventoryCount;
+
+    fn default_columns() -> &'static [&'static str] {
+        &["model", "site_id", "count"]
+    }
+
+    fn records(&self) -> Vec<&InventoryCount> {
+        self.results.iter().collect()
+    }
+}
+
+impl From<Vec<InventoryReply>> for InventoryReplies {
+    fn from(v: Vec<InventoryReply>) -> Self {
+        InventoryReplies(v)
+    }
+}
+
+impl Render for InventoryReplies {
+    type Record = InventoryReply;
+
+    fn default_columns() -> &'static [&'static str] {
+        &["op", "success", "error"]
+    }
+
+    fn records(&self) -> Vec<&InventoryReply> {
+        self.0.iter().collect()
+    }
+}
+
+impl Render for InventoryReply {
+    type Record = InventoryReply;
+
+    fn default_columns() -> &'static [&'static str] {
+        &["op", "success", "error"]
+    }
+
+    fn records(&self) -> Vec<&InventoryReply> {
+        vec![self]
+    }
+}
+
 pub fn list<'a>(
     c: &HttpClient,
-    org_id: &'a str,
-    query: Option<&'a str>,
-) -> Result<Inventories, ()> {
-    match c.get(inventories_path(org_id, query), &()) {
-        Ok(Some(inventories)) => {
+    org_id: &'a OrgId,
+    query: Option<&'a InventoryQuery>,
+) -> Result<Inventories, MistError> {
+    match c.get(inventories_path(c, org_id, query)?, &()) {
+        Ok(inventories) => {
             debug("list inventories request succeed");
             Ok(inventories)
         }
-        _ => {
-            warn("list inventories request failed");
-            Err(())
+        Err(e) => {
+            warn(&format!("list inventories request failed: {}", e));
+            Err(e)
         }
     }
 }
 
+/// Lists the whole inventory, following all pages
+pub fn list_all<'a>(
+    c: &HttpClient,
+    org_id: &'a OrgId,
+    query: Option<&'a InventoryQuery>,
+) -> Result<Inventories, MistError> {
+    let query = query.map(InventoryQuery::without_paging);
+    match c.get_all(inventories_path(c, org_id, query.as_ref())?) {
+        Ok(inventories) => {
+            debug("list all inventories request succeed");
+            Ok(Inventories(inventories))
+        }
+        Err(e) => {
+            warn(&format!("list all inventories request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+/// Pages of the inventory, fetched lazily while iterating
+pub fn list_pages<'a>(
+    c: &'a HttpClient,
+    org_id: &'a OrgId,
+    query: Option<&'a InventoryQuery>,
+) -> Result<Pages<'a, Inventory>, MistError> {
+    let query = query.map(InventoryQuery::without_paging);
+    Ok(c.paginate(inventories_path(c, org_id, query.as_ref())?))
+}
+
 pub fn claim<'a>(
     c: &HttpClient,
-    org_id: &'a str,
+    org_id: &'a OrgId,
     claims: Vec<&'a str>,
-) -> Result<ClaimDeviceReply, ()> {
-    match c.post(inventories_path(org_id, None), &claims) {
-        Ok(Some(reply)) => {
+) -> Result<ClaimDeviceReply, MistError> {
+    match c.post(inventories_path(c, org_id, None)?, &claims) {
+        Ok(reply) => {
             debug("Claim device request succeed");
             Ok(reply)
         }
-        _ => {
-            warn("Claim device request failed");
-            Err(())
+        Err(e) => {
+            warn(&format!("Claim device request failed: {}", e));
+            Err(e)
         }
     }
 }
 
 pub fn delete<'a>(
     c: &HttpClient,
-    org_id: &'a str,
+    org_id: &'a OrgId,
     serials: Vec<String>,
-    macs: Vec<String>,
-) -> Result<InventoryReply, ()> {
+    macs: Vec<MacAddr>,
+) -> Result<InventoryReply, MistError> {
     let req = DelInventory {
+        op: delete_op(),
         serials,
         macs,
-        ..Default::default()
     };
-    match c.put(inventories_path(org_id, None), &req) {
-        Ok(Some(reply)) => {
+    match c.put(inventories_path(c, org_id, None)?, &req) {
+        Ok(reply) => {
             debug("delete inventory request succeed");
             Ok(reply)
         }
-        _ => {
-            warn("delete inventory request failed");
-            Err(())
+        Err(e) => {
+            warn(&format!("delete inventory request failed: {}", e));
+            Err(e)
         }
     }
 }
 
 pub fn assign<'a>(
     c: &HttpClient,
-    org_id: &'a str,
-    site_id: &'a str,
-    macs: Vec<String>,
+    org_id: &'a OrgId,
+    site_id: &'a SiteId,
+    macs: Vec<MacAddr>,
     no_reassign: Option<bool>,
     disable_auto_config: Option<bool>,
     managed: Option<bool>,
-) -> Result<InventoryReply, ()> {
+) -> Result<InventoryReply, MistError> {
     let req = AssignInventory {
-        site_id: site_id.to_string(),
-        macs: macs,
-        no_reassign: no_reassign,
-        disable_auto_config: disable_auto_config,
-        managed: managed,
-        ..Default::default()
+        op: assign_op(),
+        site_id: site_id.clone(),
+        macs,
+        no_reassign,
+        disable_auto_config,
+        managed,
     };
-    match c.put(inventories_path(org_id, None), &req) {
-        Ok(Some(reply)) => {
+    match c.put(inventories_path(c, org_id, None)?, &req) {
+        Ok(reply) => {
             debug("assign inventory request succeed");
             Ok(reply)
         }
-        _ => {
-            warn("assign inventory request failed");
-            Err(())
+        Err(e) => {
+            warn(&format!("assign inventory request failed: {}", e));
+            Err(e)
         }
     }
 }
 
 pub fn unassign<'a>(
     c: &HttpClient,
-    org_id: &'a str,
-    macs: Vec<String>
-) -> Result<InventoryReply, ()> {
-    let req = UnassignInventory { macs: macs, ..Default::default() };
-    match c.put(inventories_path(org_id, None), &req) {
-        Ok(Some(reply)) => {
+    org_id: &'a OrgId,
+    macs: Vec<MacAddr>,
+) -> Result<InventoryReply, MistError> {
+    let req = UnassignInventory {
+        op: unassign_op(),
+        macs,
+    };
+    match c.put(inventories_path(c, org_id, None)?, &req) {
+        Ok(reply) => {
             debug("unassign inventory request succeed");
             Ok(reply)
         }
-        _ => {
-            warn("unassign inventory request failed");
-            Err(())
+        Err(e) => {
+            warn(&format!("unassign inventory request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+/// Replaces a device of a site with an unassigned one of the inventory,
+/// which takes over its name and config except the `discard`ed ones
+pub fn replace<'a>(
+    c: &HttpClient,
+    org_id: &'a OrgId,
+    req: &'a ReplaceInventory,
+) -> Result<InventoryReply, MistError> {
+    let url = c.url(&format!("orgs/{}/inventory/replace", org_id));
+    match c.post(url, req) {
+        Ok(reply) => {
+            debug("replace inventory request succeed");
+            Ok(reply)
+        }
+        Err(e) => {
+            warn(&format!("replace inventory request failed: {}", e));
+            Err(e)
         }
     }
 }
 
-/// private functions
+/// Converts cloud-ready switches and gateways to be managed by Mist
+pub fn upgrade_to_mist<'a>(
+    c: &HttpClient,
+    org_id: &'a OrgId,
+    macs: Vec<MacAddr>,
+) -> Result<InventoryReply, MistError> {
+    let req = UpgradeInventory {
+        op: upgrade_to_mist_op(),
+        macs,
+    };
+    match c.put(inventories_path(c, org_id, None)?, &req) {
+        Ok(reply) => {
+            debug("upgrade_to_mist inventory request succeed");
+            Ok(reply)
+        }
+        Err(e) => {
+            warn(&format!("upgrade_to_mist inventory request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+/// Number of devices per model or site, narrowed down by `query`. The
+/// `limit` and `page` of `query` select a page of the groups.
+pub fn count<'a>(
+    c: &HttpClient,
+    org_id: &'a OrgId,
+    by: CountBy,
+    query: Option<&'a InventoryQuery>,
+) -> Result<InventoryCounts, MistError> {
+    let mut qstr = format!("distinct={}", by.column());
+    if let Some(filters) = query {
+        let filters = filters.to_query_string()?;
+        if !filters.is_empty() {
+            qstr = format!("{}&{}", qstr, filters);
+        }
+    }
+    let url = c.url(&format!("orgs/{}/inventory/count?{}", org_id, qstr));
+    match c.get(url, &()) {
+        Ok(counts) => {
+            debug("count inventory request succeed");
+            Ok(counts)
+        }
+        Err(e) => {
+            warn(&format!("count inventory request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+/// Like `count`, following every page of the groups. `total` is the
+/// number of groups.
+pub fn count_all<'a>(
+    c: &HttpClient,
+    org_id: &'a OrgId,
+    by: CountBy,
+    query: Option<&'a InventoryQuery>,
+) -> Result<InventoryCounts, MistError> {
+    let query = query
+        .map(InventoryQuery::without_paging)
+        .unwrap_or_default();
+    let mut results = Vec::new();
+    for page in 1.. {
+        let query = query.clone().limit(c.page_size()).page(page);
+        let counts = count(c, org_id, by, Some(&query))?;
+        let fetched = counts.results.len();
+        let limit = counts.limit.unwrap_or_else(|| c.page_size());
+        results.extend(counts.results);
+        if http::is_last_page(page, limit, fetched, counts.total) {
+            break;
+        }
+    }
+    Ok(InventoryCounts {
+        distinct: Some(by.column().to_string()),
+        total: Some(results.len() as u32),
+        limit: None,
+        results,
+    })
+}
+
+/// Searches the inventory of every org the user has an org privilege
+/// on. Orgs refusing the listing are skipped with a warning.
+pub fn search<'a>(c: &HttpClient, query: &'a InventoryQuery) -> Result<Inventories, MistError> {
+    let mut org_ids: Vec<OrgId> = login::get_priviledges(c)?
+        .privileges
+        .into_iter()
+        .filter(|p| p.scope == Scope::Org)
+        .filter_map(|p| p.org_id)
+        .collect();
+    org_ids.sort();
+    org_ids.dedup();
+    let mut found = Vec::new();
+    for org_id in org_ids {
+        match list_all(c, &org_id, Some(query)) {
+            Ok(Inventories(devices)) => found.extend(devices.into_iter().map(|mut device| {
+                device.org_id.get_or_insert_with(|| org_id.clone());
+                device
+            })),
+            Err(e) if e.is_auth() => warn(&format!("skipping org {}: {}", org_id, e)),
+            Err(e) => return Err(e),
+        }
+    }
+    info(&format!("{} devices found", found.len()));
+    Ok(Inventories(found))
+}
 
-fn inventories_path<'a>(org_id: &'a str, query: Option<&'a str>) -> String {
-    if let Some(qstr) = query {
-        format!("{}/orgs/{}/inventory?{}", MIST_API_BASE, org_id, qstr)
+// private functions
+
+fn inventories_path<'a>(
+    c: &HttpClient,
+    org_id: &'a OrgId,
+    query: Option<&'a InventoryQuery>,
+) -> Result<String, MistError> {
+    let path = format!("orgs/{}/inventory", org_id);
+    match query.map(InventoryQuery::to_query_string).transpose()? {
+        Some(qstr) if !qstr.is_empty() => Ok(c.url(&format!("{}?{}", path, qstr))),
+        _ => Ok(c.url(&path)),
+    }
+}
+
+fn validate_serial<'a>(serial: &'a str) -> Result<(), MistError> {
+    if !serial.is_empty() && serial.len() <= 32 && serial.chars().all(|c| c.is_ascii_alphanumeric())
+    {
+        Ok(())
     } else {
-        format!("{}/orgs/{}/inventory", MIST_API_BASE, org_id)
+        Err(MistError::Invalid(format!(
+            "malformed serial number: {}",
+            serial
+        )))
     }
 }
 
@@ -229,3 +695,91 @@ fn assign_op() -> String {
 fn unassign_op() -> String {
     "unassign".to_string()
 }
+
+fn upgrade_to_mist_op() -> String {
+    "upgrade_to_mist".to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_encodes_values() {
+        let query = InventoryQuery::new()
+            .ty(DeviceType::Ap)
+            .model("AP43 +E&x=1");
+        assert_eq!(
+            query.to_query_string().unwrap(),
+            "type=ap&model=AP43+%2BE%26x%3D1"
+        );
+        assert_eq!(InventoryQuery::new().to_query_string().unwrap(), "");
+    }
+
+    #[test]
+    fn query_normalizes_mac() {
+        let query = InventoryQuery::new()
+            .mac("5C:5B:35:AA:BB:CC")
+            .serial("A0123456789");
+        assert_eq!(
+            query.to_query_string().unwrap(),
+            "serial=A0123456789&mac=5c5b35aabbcc"
+        );
+    }
+
+    #[test]
+    fn query_rejects_bad_mac_and_serial() {
+        for mac in ["5c5b35aabb", "5c:5b:35:aa:bb:cx", "switch"] {
+            assert!(
+                InventoryQuery::new().mac(mac).to_query_string().is_err(),
+                "{}",
+                mac
+            );
+        }
+        let too_long = "A".repeat(33);
+        for serial in [
+            "",
+            too_long.as_str(),
+            "A0123-456",
+            "A0123 456",
+            "A0123&x=1",
+            "ＡＢＣ",
+        ] {
+            assert!(
+                InventoryQuery::new()
+                    .serial(serial)
+                    .to_query_string()
+                    .is_err(),
+                "{}",
+                serial
+            );
+        }
+        let longest = "A".repeat(32);
+        assert!(InventoryQuery::new()
+            .serial(&longest)
+            .to_query_string()
+            .is_ok());
+    }
+
+    #[test]
+    fn query_serializes_flags_and_times() {
+        let site_id = SiteId::new("4ac1dcf4-9d8b-7211-65c4-057819f0862b");
+        let query = InventoryQuery::new()
+            .site_id(&site_id)
+            .unassigned(true)
+            .vc(false)
+            .modified_after(1_600_000_000)
+            .limit(100)
+            .page(2);
+        assert_eq!(
+            query.to_query_string().unwrap(),
+            "site_id=4ac1dcf4-9d8b-7211-65c4-057819f0862b&unassigned=true&vc=false\
+             &modified_after=1600000000&limit=100&page=2"
+        );
+        assert_eq!(
+            query.without_paging().to_query_string().unwrap(),
+            "site_id=4ac1dcf4-9d8b-7211-65c4-057819f0862b&unassigned=true&vc=false\
+             &modified_after=1600000000"
+        );
+    }
+}
@@ -1,4 +1,6 @@
 // Copyright 2020 Eishun Kondoh
 // SPDX-License-Identifier: Apache-2.0
 
+pub mod bulk;
 pub mod inventory;
+pub mod reconcile;
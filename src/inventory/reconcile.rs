@@ -0,0 +1,477 @@
+// Copyright 2020 Eishun Kondoh
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::error::MistError;
+use crate::http::HttpClient;
+use crate::inventory::inventory::{self, Inventory, InventoryReplies, InventoryReply};
+use crate::logger::{info, warn};
+use crate::output::Render;
+use crate::types::{MacAddr, OrgId, SiteId};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// A row of the source of truth, e.g. a CMDB export:
+///
+/// ```text
+/// serial,mac,site
+/// A0123456789,5c5b35aabbcc,Tokyo
+/// ```
+///
+/// Devices are matched by MAC address, or by serial when the MAC is
+/// empty. `site` is a site ID or name, `-` or `unassigned` for devices
+/// which should not be assigned to any site. The site of devices with an
+/// empty `site` is not checked.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExpectedDevice {
+    #[serde(default)]
+    pub serial: Option<String>,
+    #[serde(default)]
+    pub mac: Option<MacAddr>,
+    #[serde(default)]
+    pub site: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Discrepancies(Vec<Discrepancy>);
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Discrepancy {
+    pub kind: DiscrepancyKind,
+    #[serde(default)]
+    pub serial: Option<String>,
+    #[serde(default)]
+    pub mac: Option<MacAddr>,
+    #[serde(default)]
+    pub model: Option<String>,
+    // site of the source of truth
+    #[serde(default)]
+    pub expected_site_id: Option<SiteId>,
+    // site of the inventory
+    #[serde(default)]
+    pub site_id: Option<SiteId>,
+}
+
+/// The discrepancies found and, once applied, the replies of the fixes
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Reconciliation {
+    pub discrepancies: Discrepancies,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub applied: Option<InventoryReplies>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscrepancyKind {
+    // in the source of truth, not in the inventory
+    Missing,
+    // in the inventory, not in the source of truth
+    Unexpected,
+    // in both, assigned to another site than expected
+    SiteMismatch,
+}
+
+impl Discrepancies {
+    pub fn count(&self, kind: DiscrepancyKind) -> usize {
+        self.0.iter().filter(|d| d.kind == kind).count()
+    }
+}
+
+impl From<Vec<Discrepancy>> for Discrepancies {
+    fn from(v: Vec<Discrepancy>) -> Self {
+        Discrepancies(v)
+    }
+}
+
+impl Render for Discrepancies {
+    type Record = Discrepancy;
+
+    fn default_columns() -> &'static [&'static str] {
+        &[
+            "kind",
+            "serial",
+            "mac",
+            "model",
+            "expected_site_id",
+            "site_id",
+        ]
+    }
+
+    fn records(&self) -> Vec<&Discrepancy> {
+        self.0.iter().collect()
+    }
+}
+
+impl Render for Reconciliation {
+    type Record = Discrepancy;
+
+    fn default_columns() -> &'static [&'static str] {
+        Discrepancies::default_columns()
+    }
+
+    fn records(&self) -> Vec<&Discrepancy> {
+        self.discrepancies.records()
+    }
+}
+
+/// Reads the devices of a source of truth file, see `ExpectedDevice`
+pub fn read_expected<'a>(path: &'a Path) -> Result<Vec<ExpectedDevice>, MistError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_path(path)
+        .map_err(|e| MistError::Invalid(format!("{}: {}", path.display(), e)))?;
+    let mut devices = Vec::new();
+    for (i, device) in reader.deserialize::<ExpectedDevice>().enumerate() {
+        let device =
+            device.map_err(|e| MistError::Invalid(format!("{}: {}", path.display(), e)))?;
+        if device.mac.is_none() && device.serial.as_deref().is_none_or(str::is_empty) {
+            // the header is line 1
+            return Err(MistError::Invalid(format!(
+                "{}: line {}: serial or mac is required",
+                path.display(),
+                i + 2
+            )));
+        }
+        devices.push(device);
+    }
+    Ok(devices)
+}
+
+/// Compares the inventory of an org with `expected`, resolving the site
+/// names of `expected` with `site_id`
+pub fn reconcile<'a, F>(
+    c: &HttpClient,
+    org_id: &'a OrgId,
+    expected: &'a [ExpectedDevice],
+    mut site_id: F,
+) -> Result<Discrepancies, MistError>
+where
+    F: FnMut(&str) -> Result<SiteId, MistError>,
+{
+    let mut sites = BTreeMap::new();
+    for name in expected.iter().filter_map(|d| d.site.as_deref()) {
+        if !name.is_empty() && !is_unassigned(name) && !sites.contains_key(name) {
+            sites.insert(name, site_id(name)?);
+        }
+    }
+    let listing = inventory::list_all(c, org_id, None)?;
+    let inventory: Vec<&Inventory> = listing.iter().collect();
+    let found = compare(expected, &inventory, &sites);
+    info(&format!(
+        "{} missing, {} unexpected, {} site mismatches",
+        found.count(DiscrepancyKind::Missing),
+        found.count(DiscrepancyKind::Unexpected),
+        found.count(DiscrepancyKind::SiteMismatch)
+    ));
+    Ok(found)
+}
+
+/// Fixes the site mismatches: devices are assigned to their expected site,
+/// or unassigned when they should not have one, `batch_size` MAC
+/// addresses per request. Missing and unexpected devices are left alone.
+///
+/// Returns the successes and errors of all assign and unassign requests.
+pub fn apply<'a>(
+    c: &HttpClient,
+    org_id: &'a OrgId,
+    found: &'a Discrepancies,
+    batch_size: usize,
+) -> Result<InventoryReplies, MistError> {
+    let mut assigned = reply("assign");
+    let mut unassigned = reply("unassign");
+    for (site_id, batch) in batches(found, batch_size) {
+        let (summary, result) = match &site_id {
+            Some(site_id) => (
+                &mut assigned,
+                inventory::assign(c, org_id, site_id, batch.clone(), None, None, None),
+            ),
+            None => (
+                &mut unassigned,
+                inventory::unassign(c, org_id, batch.clone()),
+            ),
+        };
+        match result {
+            Ok(reply) => {
+                summary.success.extend(reply.success);
+                summary.error.extend(reply.error);
+            }
+            // the other batches may still succeed
+            Err(e) => {
+                warn(&format!(
+                    "{} of {} devices failed: {}",
+                    summary.op,
+                    batch.len(),
+                    e
+                ));
+                summary
+                    .error
+                    .extend(batch.iter().map(|mac| mac.to_string()));
+            }
+        }
+    }
+    Ok(vec![assigned, unassigned].into())
+}
+
+// private functions
+
+// site cells asking for a device to be unassigned
+fn is_unassigned<'a>(site: &'a str) -> bool {
+    site == "-" || site.eq_ignore_ascii_case("unassigned")
+}
+
+// compares `expected` with the inventory, `sites` having the IDs of the
+// site names of `expected`
+fn compare<'a>(
+    expected: &'a [ExpectedDevice],
+    inventory: &'a [&'a Inventory],
+    sites: &'a BTreeMap<&'a str, SiteId>,
+) -> Discrepancies {
+    let mut matched = vec![false; inventory.len()];
+    let mut found = Vec::new();
+    for device in expected {
+        // none when the site is not checked, Some(None) when the device
+        // should be unassigned
+        let expected_site_id = match device.site.as_deref() {
+            None | Some("") => None,
+            Some(site) if is_unassigned(site) => Some(None),
+            Some(site) => Some(sites.get(site).cloned()),
+        };
+        let index = inventory.iter().position(|inv| matches(device, inv));
+        if let Some(i) = index {
+            matched[i] = true;
+        }
+        match index.map(|i| inventory[i]) {
+            None => found.push(Discrepancy {
+                kind: DiscrepancyKind::Missing,
+                serial: device.serial.clone(),
+                mac: device.mac.clone(),
+                model: None,
+                expected_site_id: expected_site_id.flatten(),
+                site_id: None,
+            }),
+            Some(inv) => match expected_site_id {
+                Some(expected_site_id) if inv.site_id != expected_site_id => {
+                    found.push(Discrepancy {
+                        kind: DiscrepancyKind::SiteMismatch,
+                        serial: Some(inv.serial.clone()),
+                        mac: Some(inv.mac.clone()),
+                        model: Some(inv.model.clone()),
+                        expected_site_id,
+                        site_id: inv.site_id.clone(),
+                    })
+                }
+                _ => {}
+            },
+        }
+    }
+    for (inv, _) in inventory.iter().zip(matched).filter(|(_, m)| !m) {
+        found.push(Discrepancy {
+            kind: DiscrepancyKind::Unexpected,
+            serial: Some(inv.serial.clone()),
+            mac: Some(inv.mac.clone()),
+            model: Some(inv.model.clone()),
+            expected_site_id: None,
+            site_id: inv.site_id.clone(),
+        });
+    }
+    Discrepancies(found)
+}
+
+// MAC addresses of the site mismatches per expected site, none to
+// unassign, in batches of `batch_size`
+fn batches<'a>(found: &'a Discrepancies, batch_size: usize) -> Vec<(Option<SiteId>, Vec<MacAddr>)> {
+    let mut moves: BTreeMap<Option<&SiteId>, Vec<MacAddr>> = BTreeMap::new();
+    for d in found.0.iter() {
+        if let (DiscrepancyKind::SiteMismatch, Some(mac)) = (d.kind, &d.mac) {
+            let macs = moves.entry(d.expected_site_id.as_ref()).or_default();
+            macs.push(mac.clone());
+        }
+    }
+    moves
+        .into_iter()
+        .flat_map(|(site_id, macs)| {
+            macs.chunks(batch_size.max(1))
+                .map(|batch| (site_id.cloned(), batch.to_vec()))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+fn matches<'a>(expected: &'a ExpectedDevice, inv: &'a Inventory) -> bool {
+    match (&expected.mac, &expected.serial) {
+        (Some(mac), _) => mac == &inv.mac,
+        (None, Some(serial)) => serial.eq_ignore_ascii_case(&inv.serial),
+        (None, None) => false,
+    }
+}
+
+fn reply<'a>(op: &'a str) -> InventoryReply {
+    InventoryReply {
+        op: op.to_string(),
+        success: Vec::new(),
+        error: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKYO: &str = "4ac1dcf4-9d8b-7211-65c4-057819f0862b";
+    const OSAKA: &str = "5bd2edf5-0e9c-8322-76d5-168920f1973c";
+
+    fn device(mac: &str, serial: &str, site_id: Option<&str>) -> Inventory {
+        serde_json::from_value(serde_json::json!({
+            "serial": serial,
+            "id": format!("00000000-0000-0000-1000-{}", mac),
+            "model": "AP43",
+            "type": "ap",
+            "mac": mac,
+            "site_id": site_id,
+            "modified_time": 0,
+            "created_time": 0,
+        }))
+        .unwrap()
+    }
+
+    fn expected(mac: Option<&str>, serial: Option<&str>, site: Option<&str>) -> ExpectedDevice {
+        ExpectedDevice {
+            serial: serial.map(str::to_string),
+            mac: mac.map(|mac| mac.parse().unwrap()),
+            site: site.map(str::to_string),
+        }
+    }
+
+    fn sites() -> BTreeMap<&'static str, SiteId> {
+        let mut sites = BTreeMap::new();
+        sites.insert("Tokyo", SiteId::new(TOKYO));
+        sites.insert("Osaka", SiteId::new(OSAKA));
+        sites
+    }
+
+    fn kinds(found: &Discrepancies) -> Vec<(DiscrepancyKind, String)> {
+        found
+            .0
+            .iter()
+            .map(|d| {
+                let mac = d.mac.as_ref().map(|m| m.to_string()).unwrap_or_default();
+                (d.kind, mac)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn matches_by_mac_then_serial() {
+        let inv = device("5c5b35000001", "A0123", None);
+        assert!(matches(
+            &expected(Some("5C:5B:35:00:00:01"), None, None),
+            &inv
+        ));
+        // the MAC wins over a matching serial
+        assert!(!matches(
+            &expected(Some("5c5b35000002"), Some("A0123"), None),
+            &inv
+        ));
+        assert!(matches(&expected(None, Some("a0123"), None), &inv));
+        assert!(!matches(&expected(None, Some("A0124"), None), &inv));
+        assert!(!matches(&expected(None, None, None), &inv));
+    }
+
+    #[test]
+    fn compare_finds_missing_unexpected_and_mismatches() {
+        let inventory = [
+            device("5c5b35000001", "S1", Some(TOKYO)),
+            device("5c5b35000002", "S2", Some(TOKYO)),
+            device("5c5b35000003", "S3", None),
+        ];
+        let inventory: Vec<&Inventory> = inventory.iter().collect();
+        let rows = [
+            expected(Some("5c5b35000001"), None, Some("Tokyo")),
+            expected(None, Some("s2"), Some("Osaka")),
+            expected(Some("5c5b35000009"), None, Some("Tokyo")),
+        ];
+        let sites = sites();
+        let found = compare(&rows, &inventory, &sites);
+        assert_eq!(
+            kinds(&found),
+            vec![
+                (DiscrepancyKind::SiteMismatch, "5c5b35000002".to_string()),
+                (DiscrepancyKind::Missing, "5c5b35000009".to_string()),
+                (DiscrepancyKind::Unexpected, "5c5b35000003".to_string()),
+            ]
+        );
+        assert_eq!(found.0[0].expected_site_id, Some(SiteId::new(OSAKA)));
+        assert_eq!(found.0[1].expected_site_id, Some(SiteId::new(TOKYO)));
+    }
+
+    #[test]
+    fn compare_checks_sites_only_when_given() {
+        let inventory = [
+            device("5c5b35000001", "S1", Some(TOKYO)),
+            device("5c5b35000002", "S2", Some(TOKYO)),
+            device("5c5b35000003", "S3", Some(TOKYO)),
+            device("5c5b35000004", "S4", None),
+        ];
+        let inventory: Vec<&Inventory> = inventory.iter().collect();
+        let rows = [
+            expected(Some("5c5b35000001"), None, None),
+            expected(Some("5c5b35000002"), None, Some("")),
+            expected(Some("5c5b35000003"), None, Some("-")),
+            expected(Some("5c5b35000004"), None, Some("Unassigned")),
+        ];
+        let sites = sites();
+        let found = compare(&rows, &inventory, &sites);
+        assert_eq!(
+            kinds(&found),
+            vec![(DiscrepancyKind::SiteMismatch, "5c5b35000003".to_string())]
+        );
+        assert_eq!(found.0[0].expected_site_id, None);
+    }
+
+    #[test]
+    fn batches_group_mismatches_per_site() {
+        let mismatch = |mac: &str, site: Option<&str>| Discrepancy {
+            kind: DiscrepancyKind::SiteMismatch,
+            serial: None,
+            mac: Some(mac.parse().unwrap()),
+            model: None,
+            expected_site_id: site.map(SiteId::new),
+            site_id: None,
+        };
+        let found = Discrepancies(vec![
+            mismatch("5c5b35000001", Some(TOKYO)),
+            mismatch("5c5b35000002", None),
+            mismatch("5c5b35000003", Some(TOKYO)),
+            mismatch("5c5b35000004", Some(OSAKA)),
+            mismatch("5c5b35000005", Some(TOKYO)),
+            Discrepancy {
+                kind: DiscrepancyKind::Unexpected,
+                ..mismatch("5c5b35000006", None)
+            },
+        ]);
+        let grouped: Vec<(Option<String>, Vec<String>)> = batches(&found, 2)
+            .into_iter()
+            .map(|(site, macs)| {
+                (
+                    site.map(|s| s.to_string()),
+                    macs.iter().map(|m| m.to_string()).collect(),
+                )
+            })
+            .collect();
+        let macs = |v: &[&str]| v.iter().map(|m| m.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            grouped,
+            vec![
+                (None, macs(&["5c5b35000002"])),
+                (
+                    Some(TOKYO.to_string()),
+                    macs(&["5c5b35000001", "5c5b35000003"])
+                ),
+                (Some(TOKYO.to_string()), macs(&["5c5b35000005"])),
+                (Some(OSAKA.to_string()), macs(&["5c5b35000004"])),
+            ]
+        );
+        // a batch size of 0 is taken as 1
+        assert_eq!(batches(&found, 0).len(), 5);
+    }
+}
@@ -1,6 +1,12 @@
 // Copyright 2020 Eishun Kondoh
 // SPDX-License-Identifier: Apache-2.0
 
+#![allow(
+    clippy::module_inception,
+    clippy::needless_lifetimes,
+    clippy::redundant_static_lifetimes
+)]
+
 extern crate clap;
 extern crate log;
 extern crate pretty_env_logger;
@@ -8,21 +14,36 @@ extern crate prettytable;
 extern crate serde;
 extern crate serde_json;
 
+pub mod audit;
 pub mod auth;
+pub mod budget;
+pub mod cli;
+pub mod config;
 pub mod consts;
+pub mod error;
 pub mod http;
+pub mod inventory;
 pub mod logger;
+pub mod msp;
 pub mod org;
+pub mod output;
+pub mod resolver;
+pub mod retry;
+pub mod session;
 pub mod sites;
+pub mod types;
+pub mod webhooks;
 pub mod wlans;
 
 pub use crate::auth::paths::*;
 pub use crate::auth::*;
 
+use clap::Clap;
+
 fn main() {
     pretty_env_logger::init();
-    match http::HttpClient::new() {
-        Ok(_c) => (),
-        Err(_) => logger::warn("API_TOKEN is not configured"),
+    if let Err(e) = cli::run(cli::Opts::parse()) {
+        logger::error(&format!("{}", e));
+        std::process::exit(1);
     }
 }
@@ -0,0 +1,347 @@
+// Copyright 2020 Eishun Kondoh
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::error::MistError;
+use crate::http::HttpClient;
+use crate::logger::{debug, warn};
+use crate::org::org::{Org, OrgStats, Orgs};
+use crate::output::Render;
+use crate::types::{MspId, OrgId};
+use serde::{Deserialize, Serialize};
+
+///
+/// ref: https://api.mist.com/api/v1/docs/MSP
+///
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Msp {
+    pub id: MspId,
+    pub name: String,
+    #[serde(default)]
+    pub tier: Option<String>,
+    #[serde(default)]
+    pub created_time: Option<u32>,
+    #[serde(default)]
+    pub modified_time: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct MspOrgStats(Vec<OrgStats>);
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct OrgGroups(Vec<OrgGroup>);
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OrgGroup {
+    // id, msp_id and timestamps are assigned by the API
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub org_ids: Vec<OrgId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub msp_id: Option<MspId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_time: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified_time: Option<u32>,
+}
+
+/// Devices connected and disconnected per org of an MSP, with a `total`
+/// row last
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct DeviceRollup(Vec<OrgDevices>);
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct OrgDevices {
+    // none for the total row
+    #[serde(default)]
+    pub org_id: Option<OrgId>,
+    pub name: String,
+    pub num_sites: u32,
+    pub num_devices: u32,
+    pub num_devices_connected: u32,
+    pub num_devices_disconnected: u32,
+    // share of the devices connected, 0-100
+    pub connected_percent: f64,
+}
+
+impl From<Vec<OrgStats>> for MspOrgStats {
+    fn from(v: Vec<OrgStats>) -> Self {
+        MspOrgStats(v)
+    }
+}
+
+impl From<Vec<OrgGroup>> for OrgGroups {
+    fn from(v: Vec<OrgGroup>) -> Self {
+        OrgGroups(v)
+    }
+}
+
+impl From<Vec<OrgDevices>> for DeviceRollup {
+    fn from(v: Vec<OrgDevices>) -> Self {
+        DeviceRollup(v)
+    }
+}
+
+impl OrgDevices {
+    fn add(&mut self, stats: &OrgStats) {
+        self.num_sites += u32::from(stats.num_sites);
+        self.num_devices += stats.num_devices;
+        self.num_devices_connected += u32::from(stats.num_devices_connected);
+        self.num_devices_disconnected += u32::from(stats.num_devices_disconnected);
+        let known = self.num_devices_connected + self.num_devices_disconnected;
+        self.connected_percent = if known == 0 {
+            0.0
+        } else {
+            (f64::from(self.num_devices_connected) * 1000.0 / f64::from(known)).round() / 10.0
+        };
+    }
+}
+
+impl Render for Msp {
+    type Record = Msp;
+
+    fn default_columns() -> &'static [&'static str] {
+        &["id", "name", "tier"]
+    }
+
+    fn records(&self) -> Vec<&Msp> {
+        vec![self]
+    }
+}
+
+impl Render for MspOrgStats {
+    type Record = OrgStats;
+
+    fn default_columns() -> &'static [&'static str] {
+        &[
+            "id",
+            "name",
+            "num_sites",
+            "num_inventory",
+            "num_devices",
+            "num_devices_connected",
+            "num_devices_disconnected",
+        ]
+    }
+
+    fn records(&self) -> Vec<&OrgStats> {
+        self.0.iter().collect()
+    }
+}
+
+impl Render for OrgGroups {
+    type Record = OrgGroup;
+
+    fn default_columns() -> &'static [&'static str] {
+        &["id", "name", "org_ids"]
+    }
+
+    fn records(&self) -> Vec<&OrgGroup> {
+        self.0.iter().collect()
+    }
+}
+
+impl Render for OrgGroup {
+    type Record = OrgGroup;
+
+    fn default_columns() -> &'static [&'static str] {
+        &["id", "name", "org_ids"]
+    }
+
+    fn records(&self) -> Vec<&OrgGroup> {
+        vec![self]
+    }
+}
+
+impl Render for DeviceRollup {
+    type Record = OrgDevices;
+
+    fn default_columns() -> &'static [&'static str] {
+        &[
+            "org_id",
+            "name",
+            "num_sites",
+            "num_devices",
+            "num_devices_connected",
+            "num_devices_disconnected",
+            "connected_percent",
+        ]
+    }
+
+    fn records(&self) -> Vec<&OrgDevices> {
+        self.0.iter().collect()
+    }
+}
+
+pub fn get<'a>(c: &HttpClient, msp_id: &'a MspId) -> Result<Msp, MistError> {
+    match c.get(c.url(&format!("msps/{}", msp_id)), &()) {
+        Ok(msp) => {
+            debug("get msp request succeed");
+            Ok(msp)
+        }
+        Err(e) => {
+            warn(&format!("get msp request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+/// Lists every org of an MSP, following all pages
+pub fn list_orgs<'a>(c: &HttpClient, msp_id: &'a MspId) -> Result<Orgs, MistError> {
+    match c.get_all::<Org>(c.url(&format!("msps/{}/orgs", msp_id))) {
+        Ok(orgs) => {
+            debug("list msp orgs request succeed");
+            Ok(orgs.into())
+        }
+        Err(e) => {
+            warn(&format!("list msp orgs request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+/// Stats of every org of an MSP, following all pages
+pub fn list_org_stats<'a>(c: &HttpClient, msp_id: &'a MspId) -> Result<MspOrgStats, MistError> {
+    match c.get_all(c.url(&format!("msps/{}/stats/orgs", msp_id))) {
+        Ok(stats) => {
+            debug("list msp org stats request succeed");
+            Ok(MspOrgStats(stats))
+        }
+        Err(e) => {
+            warn(&format!("list msp org stats request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+/// Creates an org managed by the MSP
+pub fn create_org<'a>(c: &HttpClient, msp_id: &'a MspId, mut org: Org) -> Result<Org, MistError> {
+    org.msp_id = Some(msp_id.clone());
+    match c.post(c.url("orgs"), &org) {
+        Ok(org) => {
+            debug("create msp org request succeed");
+            Ok(org)
+        }
+        Err(e) => {
+            warn(&format!("create msp org request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+/// Devices connected and disconnected in each org of the MSP
+pub fn device_rollup<'a>(c: &HttpClient, msp_id: &'a MspId) -> Result<DeviceRollup, MistError> {
+    let stats = list_org_stats(c, msp_id)?;
+    let mut total = OrgDevices {
+        name: "total".to_string(),
+        ..Default::default()
+    };
+    let mut rows = Vec::new();
+    for org in stats.0.iter() {
+        let mut row = OrgDevices {
+            org_id: Some(org.id.clone()),
+            name: org.name.clone(),
+            ..Default::default()
+        };
+        row.add(org);
+        total.add(org);
+        rows.push(row);
+    }
+    rows.sort_by(|a, b| a.name.cmp(&b.name));
+    rows.push(total);
+    Ok(DeviceRollup(rows))
+}
+
+pub fn list_orggroups<'a>(c: &HttpClient, msp_id: &'a MspId) -> Result<OrgGroups, MistError> {
+    match c.get_all(orggroups_path(c, msp_id)) {
+        Ok(groups) => {
+            debug("list org groups request succeed");
+            Ok(OrgGroups(groups))
+        }
+        Err(e) => {
+            warn(&format!("list org groups request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+pub fn get_orggroup<'a>(
+    c: &HttpClient,
+    msp_id: &'a MspId,
+    group_id: &'a str,
+) -> Result<OrgGroup, MistError> {
+    match c.get(orggroup_path(c, msp_id, group_id), &()) {
+        Ok(group) => {
+            debug("get org group request succeed");
+            Ok(group)
+        }
+        Err(e) => {
+            warn(&format!("get org group request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+pub fn create_orggroup<'a>(
+    c: &HttpClient,
+    msp_id: &'a MspId,
+    group: &'a OrgGroup,
+) -> Result<OrgGroup, MistError> {
+    match c.post(orggroups_path(c, msp_id), group) {
+        Ok(group) => {
+            debug("create org group request succeed");
+            Ok(group)
+        }
+        Err(e) => {
+            warn(&format!("create org group request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+pub fn update_orggroup<'a>(
+    c: &HttpClient,
+    msp_id: &'a MspId,
+    group_id: &'a str,
+    group: &'a OrgGroup,
+) -> Result<OrgGroup, MistError> {
+    match c.put(orggroup_path(c, msp_id, group_id), group) {
+        Ok(group) => {
+            debug("update org group request succeed");
+            Ok(group)
+        }
+        Err(e) => {
+            warn(&format!("update org group request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+pub fn delete_orggroup<'a>(
+    c: &HttpClient,
+    msp_id: &'a MspId,
+    group_id: &'a str,
+) -> Result<(), MistError> {
+    match c.delete(orggroup_path(c, msp_id, group_id), &()) {
+        Ok(_) => {
+            debug("delete org group request succeed");
+            Ok(())
+        }
+        Err(e) => {
+            warn(&format!("delete org group request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+// private functions
+
+fn orggroups_path<'a>(c: &HttpClient, msp_id: &'a MspId) -> String {
+    c.url(&format!("msps/{}/orggroups", msp_id))
+}
+
+fn orggroup_path<'a>(c: &HttpClient, msp_id: &'a MspId, group_id: &'a str) -> String {
+    c.url(&format!("msps/{}/orggroups/{}", msp_id, group_id))
+}
@@ -0,0 +1,278 @@
+// Copyright 2020 Eishun Kondoh
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::auth::login::Privilege;
+use crate::auth::tokens::TokenPrivilege;
+use crate::error::MistError;
+use crate::http::HttpClient;
+use crate::logger::{debug, info, warn};
+use crate::output::Render;
+use crate::types::OrgId;
+use serde::{Deserialize, Serialize};
+
+///
+/// ref: https://api.mist.com/api/v1/docs/Org#admins
+///
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Admins(Vec<Admin>);
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Admin {
+    pub admin_id: String,
+    pub email: String,
+    #[serde(default)]
+    pub first_name: String,
+    #[serde(default)]
+    pub last_name: String,
+    #[serde(default)]
+    pub privileges: Vec<Privilege>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Invites(Vec<Invite>);
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Invite {
+    pub id: String,
+    pub email: String,
+    #[serde(default)]
+    pub first_name: String,
+    #[serde(default)]
+    pub last_name: String,
+    #[serde(default)]
+    pub privileges: Vec<TokenPrivilege>,
+    // epoch seconds after which the invite can not be accepted
+    #[serde(default)]
+    pub expire_time: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InviteParams {
+    pub email: String,
+    pub first_name: String,
+    pub last_name: String,
+    // hours the invite is valid for, 24 by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hours: Option<u32>,
+    pub privileges: Vec<TokenPrivilege>,
+}
+
+#[derive(Serialize, Debug)]
+struct AdminUpdate<'a> {
+    privileges: &'a [TokenPrivilege],
+}
+
+impl Admins {
+    pub fn iter(&self) -> impl Iterator<Item = &Admin> {
+        self.0.iter()
+    }
+}
+
+impl From<Vec<Admin>> for Admins {
+    fn from(v: Vec<Admin>) -> Self {
+        Admins(v)
+    }
+}
+
+impl Invites {
+    pub fn iter(&self) -> impl Iterator<Item = &Invite> {
+        self.0.iter()
+    }
+}
+
+impl From<Vec<Invite>> for Invites {
+    fn from(v: Vec<Invite>) -> Self {
+        Invites(v)
+    }
+}
+
+impl Render for Admins {
+    type Record = Admin;
+
+    fn default_columns() -> &'static [&'static str] {
+        &["admin_id", "email", "first_name", "last_name"]
+    }
+
+    fn records(&self) -> Vec<&Admin> {
+        self.0.iter().collect()
+    }
+}
+
+impl Render for Admin {
+    type Record = Privilege;
+
+    fn default_columns() -> &'static [&'static str] {
+        &["scope", "role", "name", "site_id", "sitegroup_id"]
+    }
+
+    fn records(&self) -> Vec<&Privilege> {
+        self.privileges.iter().collect()
+    }
+}
+
+impl Render for Invites {
+    type Record = Invite;
+
+    fn default_columns() -> &'static [&'static str] {
+        &["id", "email", "first_name", "last_name", "expire_time"]
+    }
+
+    fn records(&self) -> Vec<&Invite> {
+        self.0.iter().collect()
+    }
+}
+
+impl Render for Invite {
+    type Record = Invite;
+
+    fn default_columns() -> &'static [&'static str] {
+        &["id", "email", "first_name", "last_name", "expire_time"]
+    }
+
+    fn records(&self) -> Vec<&Invite> {
+        vec![self]
+    }
+}
+
+pub fn list<'a>(c: &HttpClient, org_id: &'a OrgId) -> Result<Admins, MistError> {
+    match c.get(admins_path(c, org_id), &()) {
+        Ok(admins) => {
+            debug("list admins request succeed");
+            Ok(admins)
+        }
+        Err(e) => {
+            warn(&format!("list admins request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+pub fn get<'a>(c: &HttpClient, org_id: &'a OrgId, admin_id: &'a str) -> Result<Admin, MistError> {
+    match c.get(admin_path(c, org_id, admin_id), &()) {
+        Ok(admin) => {
+            debug("get admin request succeed");
+            Ok(admin)
+        }
+        Err(e) => {
+            warn(&format!("get admin request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+/// Replaces the privileges of an admin on the org
+pub fn update<'a>(
+    c: &HttpClient,
+    org_id: &'a OrgId,
+    admin_id: &'a str,
+    privileges: &'a [TokenPrivilege],
+) -> Result<Admin, MistError> {
+    match c.put(admin_path(c, org_id, admin_id), &AdminUpdate { privileges }) {
+        Ok(admin) => {
+            debug("update admin request succeed");
+            Ok(admin)
+        }
+        Err(e) => {
+            warn(&format!("update admin request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+/// Removes every privilege of an admin on the org
+pub fn revoke<'a>(c: &HttpClient, org_id: &'a OrgId, admin_id: &'a str) -> Result<(), MistError> {
+    match c.delete(admin_path(c, org_id, admin_id), &()) {
+        Ok(_) => {
+            info(&format!("admin {} revoked", admin_id));
+            Ok(())
+        }
+        Err(e) => {
+            warn(&format!("revoke admin request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+/// Invites which have not been accepted yet
+pub fn list_invites<'a>(c: &HttpClient, org_id: &'a OrgId) -> Result<Invites, MistError> {
+    match c.get(invites_path(c, org_id), &()) {
+        Ok(invites) => {
+            debug("list invites request succeed");
+            Ok(invites)
+        }
+        Err(e) => {
+            warn(&format!("list invites request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+/// Invites a person by email to become an admin with `privileges`
+pub fn invite<'a>(
+    c: &HttpClient,
+    org_id: &'a OrgId,
+    params: &'a InviteParams,
+) -> Result<Invite, MistError> {
+    match c.post(invites_path(c, org_id), params) {
+        Ok(invite) => {
+            debug("create invite request succeed");
+            Ok(invite)
+        }
+        Err(e) => {
+            warn(&format!("create invite request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+/// Sends the email of a pending invite again
+pub fn resend_invite<'a>(
+    c: &HttpClient,
+    org_id: &'a OrgId,
+    invite_id: &'a str,
+) -> Result<(), MistError> {
+    let url = c.url(&format!("orgs/{}/invites/{}/resend", org_id, invite_id));
+    match c.post::<_, serde_json::Value>(url, &()) {
+        Ok(_) => {
+            debug("resend invite request succeed");
+            Ok(())
+        }
+        Err(e) => {
+            warn(&format!("resend invite request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+pub fn delete_invite<'a>(
+    c: &HttpClient,
+    org_id: &'a OrgId,
+    invite_id: &'a str,
+) -> Result<(), MistError> {
+    let url = c.url(&format!("orgs/{}/invites/{}", org_id, invite_id));
+    match c.delete(url, &()) {
+        Ok(_) => {
+            debug("delete invite request succeed");
+            Ok(())
+        }
+        Err(e) => {
+            warn(&format!("delete invite request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+// private functions
+
+fn admins_path<'a>(c: &HttpClient, org_id: &'a OrgId) -> String {
+    c.url(&format!("orgs/{}/admins", org_id))
+}
+
+fn admin_path<'a>(c: &HttpClient, org_id: &'a OrgId, admin_id: &'a str) -> String {
+    c.url(&format!("orgs/{}/admins/{}", org_id, admin_id))
+}
+
+fn invites_path<'a>(c: &HttpClient, org_id: &'a OrgId) -> String {
+    c.url(&format!("orgs/{}/invites", org_id))
+}
@@ -0,0 +1,383 @@
+// Copyright 2020 Eishun Kondoh
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::error::MistError;
+use crate::http::HttpClient;
+use crate::inventory::inventory::{self, ClaimedDevice, CountBy, InventoryQuery};
+use crate::logger::{debug, info, warn};
+use crate::output::Render;
+use crate::types::{DeviceType, OrgId};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+///
+/// ref: https://api.mist.com/api/v1/docs/Org#licenses
+///
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Licenses {
+    #[serde(default)]
+    pub subscriptions: Vec<Subscription>,
+    // quantity of the active subscriptions per type
+    #[serde(default)]
+    pub entitled: BTreeMap<String, u32>,
+    // quantity in use per type
+    #[serde(default)]
+    pub summary: BTreeMap<String, u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Subscription {
+    #[serde(default)]
+    pub subscription_id: Option<String>,
+    #[serde(default)]
+    pub order_id: Option<String>,
+    #[serde(rename = "type")]
+    pub ty: SubscriptionType,
+    pub quantity: u32,
+    #[serde(default)]
+    pub remaining_quantity: Option<u32>,
+    // devices using the subscription
+    #[serde(default)]
+    pub usage: Option<u32>,
+    // epoch seconds
+    pub start_time: u64,
+    pub end_time: u64,
+}
+
+/// SKU of a subscription
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(from = "String", into = "String")]
+pub enum SubscriptionType {
+    // Wi-Fi management, one per AP
+    Man,
+    // Marvis virtual network assistant
+    Vna,
+    // asset visibility
+    Ast,
+    // user engagement
+    Eng,
+    // premium analytics
+    Pma,
+    // EX switch management by port count, e.g. SUB-EX24
+    Ex(String),
+    // SRX and WAN gateway management, e.g. SUB-SRX1
+    Wan(String),
+    Unknown(String),
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct LicenseClaimReply {
+    #[serde(default)]
+    pub license_added: Vec<Subscription>,
+    #[serde(default)]
+    pub license_duplicated: Vec<Subscription>,
+    #[serde(default)]
+    pub license_error: Vec<serde_json::Value>,
+    // devices of the activation code, added to the inventory
+    #[serde(default)]
+    pub inventory_added: Vec<ClaimedDevice>,
+    #[serde(default)]
+    pub reason: Vec<String>,
+}
+
+/// Entitlement against the devices of each type, and the subscriptions
+/// ending soon
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct LicenseReport {
+    pub usage: Vec<LicenseUsage>,
+    // active subscriptions ending within the days of the report
+    pub expiring: Vec<Subscription>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LicenseUsage {
+    pub device_type: DeviceType,
+    // quantity of the active management subscriptions
+    pub entitled: u32,
+    // devices of the type in the inventory
+    pub devices: u32,
+    // devices beyond the entitlement
+    pub shortfall: u32,
+    // entitlement ending within the days of the report
+    pub expiring: u32,
+    // earliest end of the active subscriptions, epoch seconds
+    #[serde(default)]
+    pub next_end_time: Option<u64>,
+}
+
+#[derive(Serialize, Debug)]
+struct LicenseClaim<'a> {
+    code: &'a str,
+    #[serde(rename = "type")]
+    ty: &'static str,
+}
+
+#[derive(Serialize, Debug)]
+struct LicenseMove<'a> {
+    op: &'static str,
+    subscription_id: &'a str,
+    dst_org_id: &'a OrgId,
+    quantity: u32,
+}
+
+impl SubscriptionType {
+    pub fn as_str(&self) -> &str {
+        match self {
+            SubscriptionType::Man => "SUB-MAN",
+            SubscriptionType::Vna => "SUB-VNA",
+            SubscriptionType::Ast => "SUB-AST",
+            SubscriptionType::Eng => "SUB-ENG",
+            SubscriptionType::Pma => "SUB-PMA",
+            SubscriptionType::Ex(s) | SubscriptionType::Wan(s) | SubscriptionType::Unknown(s) => s,
+        }
+    }
+
+    /// device type a management subscription is needed for, none for
+    /// add-on subscriptions
+    pub fn device_type(&self) -> Option<DeviceType> {
+        match self {
+            SubscriptionType::Man => Some(DeviceType::Ap),
+            SubscriptionType::Ex(_) => Some(DeviceType::Switch),
+            SubscriptionType::Wan(_) => Some(DeviceType::Gateway),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SubscriptionType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<String> for SubscriptionType {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "SUB-MAN" => SubscriptionType::Man,
+            "SUB-VNA" => SubscriptionType::Vna,
+            "SUB-AST" => SubscriptionType::Ast,
+            "SUB-ENG" => SubscriptionType::Eng,
+            "SUB-PMA" => SubscriptionType::Pma,
+            _ if s.starts_with("SUB-EX") => SubscriptionType::Ex(s),
+            _ if s.starts_with("SUB-SRX") || s.starts_with("SUB-WAN") => SubscriptionType::Wan(s),
+            _ => SubscriptionType::Unknown(s),
+        }
+    }
+}
+
+impl From<SubscriptionType> for String {
+    fn from(v: SubscriptionType) -> Self {
+        v.as_str().to_string()
+    }
+}
+
+impl Subscription {
+    pub fn is_active(&self, now: u64) -> bool {
+        self.start_time <= now && now < self.end_time
+    }
+
+    /// active and ending within `days`
+    pub fn expires_within(&self, now: u64, days: u32) -> bool {
+        self.is_active(now) && self.end_time <= now + u64::from(days) * 86400
+    }
+}
+
+impl LicenseReport {
+    /// number of device types short of entitlement and subscriptions
+    /// ending soon
+    pub fn flagged(&self) -> usize {
+        self.usage.iter().filter(|u| u.shortfall > 0).count() + self.expiring.len()
+    }
+}
+
+impl Render for Licenses {
+    type Record = Subscription;
+
+    fn default_columns() -> &'static [&'static str] {
+        &[
+            "subscription_id",
+            "type",
+            "quantity",
+            "usage",
+            "start_time",
+            "end_time",
+        ]
+    }
+
+    fn records(&self) -> Vec<&Subscription> {
+        self.subscriptions.iter().collect()
+    }
+}
+
+impl Render for LicenseClaimReply {
+    type Record = Subscription;
+
+    fn default_columns() -> &'static [&'static str] {
+        &[
+            "subscription_id",
+            "type",
+            "quantity",
+            "start_time",
+            "end_time",
+        ]
+    }
+
+    fn records(&self) -> Vec<&Subscription> {
+        self.license_added.iter().collect()
+    }
+}
+
+impl Render for LicenseReport {
+    type Record = LicenseUsage;
+
+    fn default_columns() -> &'static [&'static str] {
+        &[
+            "device_type",
+            "entitled",
+            "devices",
+            "shortfall",
+            "expiring",
+            "next_end_time",
+        ]
+    }
+
+    fn records(&self) -> Vec<&LicenseUsage> {
+        self.usage.iter().collect()
+    }
+}
+
+pub fn list<'a>(c: &HttpClient, org_id: &'a OrgId) -> Result<Licenses, MistError> {
+    match c.get(licenses_path(c, org_id), &()) {
+        Ok(licenses) => {
+            debug("list licenses request succeed");
+            Ok(licenses)
+        }
+        Err(e) => {
+            warn(&format!("list licenses request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+/// Claims the subscriptions and devices of an activation code
+pub fn claim<'a>(
+    c: &HttpClient,
+    org_id: &'a OrgId,
+    code: &'a str,
+) -> Result<LicenseClaimReply, MistError> {
+    let url = c.url(&format!("orgs/{}/claim", org_id));
+    match c.post(url, &LicenseClaim { code, ty: "all" }) {
+        Ok(reply) => {
+            debug("claim license request succeed");
+            Ok(reply)
+        }
+        Err(e) => {
+            warn(&format!("claim license request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+/// Moves `quantity` of a subscription to another org
+pub fn move_license<'a>(
+    c: &HttpClient,
+    org_id: &'a OrgId,
+    subscription_id: &'a str,
+    dst_org_id: &'a OrgId,
+    quantity: u32,
+) -> Result<(), MistError> {
+    let op = LicenseMove {
+        op: "move",
+        subscription_id,
+        dst_org_id,
+        quantity,
+    };
+    match c.put::<_, serde_json::Value>(licenses_path(c, org_id), &op) {
+        Ok(_) => {
+            info(&format!(
+                "{} of subscription {} moved to org {}",
+                quantity, subscription_id, dst_org_id
+            ));
+            Ok(())
+        }
+        Err(e) => {
+            warn(&format!("move license request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+/// Compares the active management subscriptions with the devices of
+/// each type in the inventory, and lists the subscriptions ending within
+/// `days`. Both are also logged as warnings.
+pub fn report<'a>(
+    c: &HttpClient,
+    org_id: &'a OrgId,
+    days: u32,
+) -> Result<LicenseReport, MistError> {
+    let licenses = list(c, org_id)?;
+    let now = now();
+    let mut usage = Vec::new();
+    for ty in [DeviceType::Ap, DeviceType::Switch, DeviceType::Gateway].iter() {
+        let query = InventoryQuery::new().ty(ty.clone());
+        // total is the number of models, not of devices
+        let counts = inventory::count_all(c, org_id, CountBy::Model, Some(&query))?;
+        let devices = counts.results.iter().map(|r| r.count).sum();
+        let subscriptions: Vec<&Subscription> = licenses
+            .subscriptions
+            .iter()
+            .filter(|s| s.is_active(now) && s.ty.device_type().as_ref() == Some(ty))
+            .collect();
+        let entitled = subscriptions.iter().map(|s| s.quantity).sum();
+        let row = LicenseUsage {
+            device_type: ty.clone(),
+            entitled,
+            devices,
+            shortfall: devices.saturating_sub(entitled),
+            expiring: subscriptions
+                .iter()
+                .filter(|s| s.expires_within(now, days))
+                .map(|s| s.quantity)
+                .sum(),
+            next_end_time: subscriptions.iter().map(|s| s.end_time).min(),
+        };
+        if row.shortfall > 0 {
+            warn(&format!(
+                "{} {} devices but {} entitled",
+                devices, ty, entitled
+            ));
+        }
+        usage.push(row);
+    }
+    let expiring: Vec<Subscription> = licenses
+        .subscriptions
+        .into_iter()
+        .filter(|s| s.expires_within(now, days))
+        .collect();
+    for s in expiring.iter() {
+        warn(&format!(
+            "{} {} of subscription {} end in {} days",
+            s.quantity,
+            s.ty,
+            s.subscription_id.as_deref().unwrap_or("-"),
+            (s.end_time - now) / 86400
+        ));
+    }
+    Ok(LicenseReport { usage, expiring })
+}
+
+// private functions
+
+fn licenses_path<'a>(c: &HttpClient, org_id: &'a OrgId) -> String {
+    c.url(&format!("orgs/{}/licenses", org_id))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::from_secs(0))
+        .as_secs()
+}
@@ -1,4 +1,8 @@
 // Copyright 2020 Eishun Kondoh
 // SPDX-License-Identifier: Apache-2.0
 
+pub mod admins;
+pub mod licenses;
 pub mod org;
+pub mod roster;
+pub mod verify;
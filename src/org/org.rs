@@ -1,9 +1,11 @@
 // Copyright 2020 Eishun Kondoh
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::consts::MIST_API_BASE;
+use crate::error::MistError;
 use crate::http::HttpClient;
 use crate::logger::{debug, warn};
+use crate::output::Render;
+use crate::types::{Facility, MspId, OrgId, Severity, SiteId, SyslogProtocol};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -16,7 +18,13 @@ pub struct Orgs(Vec<Org>);
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Org {
+    // assigned by the API
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<OrgId>,
     pub name: String,
+    // MSP managing the org, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub msp_id: Option<MspId>,
     #[serde(default)]
     pub session_expiry: Option<u16>,
     #[serde(default)]
@@ -30,7 +38,7 @@ pub struct Org {
 #[derive(Serialize, Deserialize, Debug)]
 pub struct OrgStats {
     pub name: String,
-    pub id: String,
+    pub id: OrgId,
     #[serde(default)]
     pub orggroup_ids: Option<Vec<String>>,
     pub allow_mist: bool,
@@ -46,8 +54,8 @@ pub struct OrgSetting {
     pub id: String,
     #[serde(default = "default_disabled")]
     pub for_site: bool,
-    pub site_id: String,
-    pub org_id: String,
+    pub site_id: SiteId,
+    pub org_id: OrgId,
     pub created_time: u32,
     pub modified_time: u32,
     pub tags: Vec<String>,
@@ -55,59 +63,63 @@ pub struct OrgSetting {
 
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct OrgSettingParams {
-    pub name: String,
-    #[serde(default)]
+    // settings left out are not sent, and so not changed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub password_policy: Option<PasswordPolicy>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub ui_idle_timeout: Option<u16>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub mgmt: Option<Management>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub disable_pcap: Option<bool>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub pcap: Option<Pcap>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub pcap_bucket_verified: Option<bool>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub security: Option<Security>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub installer: Option<Installer>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub remote_syslog: Option<RemoteSyslog>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub auto_site_assignment: Option<AutoSiteAssignment>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub auto_device_naming: Option<AutoDeviceNaming>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub cloudshark: Option<CloudShark>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub auto_deviceprofile_assignment: Option<AutoDeviceProfile>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub cacerts: Option<Vec<String>>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub device_cert: Option<DeviceCert>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub tags: Option<Vec<String>>,
 }
 
+// flags left out of a nested setting are not sent either, so that a
+// partial object does not switch them on or off
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct PasswordPolicy {
-    #[serde(default = "default_enabled")]
-    pub enabled: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
     pub freshness: u32,
     pub min_length: u32,
-    #[serde(default = "default_disabled")]
-    pub requires_special_char: bool,
-    #[serde(default = "default_disabled")]
-    pub requires_two_factor_auth: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requires_special_char: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requires_two_factor_auth: Option<bool>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct Management {
-    #[serde(default = "default_disabled")]
-    pub use_wxtunnel: bool,
-    #[serde(default = "default_disabled")]
-    pub use_mxtunnel: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub use_wxtunnel: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub use_mxtunnel: Option<bool>,
     pub mxtunnel_ids: Vec<String>,
 }
 
@@ -119,27 +131,27 @@ pub struct Pcap {
 
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct Security {
-    #[serde(default = "default_disabled")]
-    pub disable_local_ssh: bool,
-    #[serde(default = "default_disabled")]
-    pub limit_ssh_access: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disable_local_ssh: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit_ssh_access: Option<bool>,
     pub fips_zeroize_password: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct Installer {
     pub grace_period: u32,
-    pub extra_site_ids: Vec<String>,
-    #[serde(default = "default_disabled")]
-    pub allow_all_sites: bool,
+    pub extra_site_ids: Vec<SiteId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allow_all_sites: Option<bool>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct RemoteSyslog {
-    #[serde(default = "default_disabled")]
-    pub enabled: bool,
-    #[serde(default = "default_disabled")]
-    pub send_to_all_servers: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub send_to_all_servers: Option<bool>,
     pub servers: Vec<RemoteSyslogServer>,
 }
 
@@ -147,23 +159,23 @@ pub struct RemoteSyslog {
 pub struct RemoteSyslogServer {
     pub host: String,
     pub port: u16,
-    pub protocol: String,
-    pub facility: String,
-    pub severity: String,
+    pub protocol: SyslogProtocol,
+    pub facility: Facility,
+    pub severity: Severity,
     pub tag: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct AutoSiteAssignment {
-    #[serde(default = "default_disabled")]
-    pub enable: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enable: Option<bool>,
     pub rules: Vec<HashMap<String, String>>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct AutoDeviceNaming {
-    #[serde(default = "default_disabled")]
-    pub enable: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enable: Option<bool>,
     pub rules: Vec<HashMap<String, String>>,
 }
 
@@ -176,8 +188,8 @@ pub struct CloudShark {
 
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct AutoDeviceProfile {
-    #[serde(default = "default_disabled")]
-    pub enable: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enable: Option<bool>,
     pub rules: Vec<HashMap<String, String>>,
 }
 
@@ -190,116 +202,219 @@ pub struct DeviceCert {
     pub key: String,
 }
 
-pub fn get_setting<'a>(c: &HttpClient, org_id: &'a str) -> Result<OrgSetting, ()> {
-    match c.get(org_setting_path(org_id), &()) {
-        Ok(Some(orgs)) => {
+impl From<Vec<Org>> for Orgs {
+    fn from(v: Vec<Org>) -> Self {
+        Orgs(v)
+    }
+}
+
+impl Render for Orgs {
+    type Record = Org;
+
+    fn default_columns() -> &'static [&'static str] {
+        &["id", "name", "allow_mist", "session_expiry"]
+    }
+
+    fn records(&self) -> Vec<&Org> {
+        self.0.iter().collect()
+    }
+}
+
+impl Render for Org {
+    type Record = Org;
+
+    fn default_columns() -> &'static [&'static str] {
+        &["name", "allow_mist", "session_expiry"]
+    }
+
+    fn records(&self) -> Vec<&Org> {
+        vec![self]
+    }
+}
+
+impl Render for OrgStats {
+    type Record = OrgStats;
+
+    fn default_columns() -> &'static [&'static str] {
+        &[
+            "id",
+            "name",
+            "num_sites",
+            "num_inventory",
+            "num_devices",
+            "num_devices_connected",
+            "num_devices_disconnected",
+        ]
+    }
+
+    fn records(&self) -> Vec<&OrgStats> {
+        vec![self]
+    }
+}
+
+impl Render for OrgSetting {
+    type Record = OrgSetting;
+
+    fn default_columns() -> &'static [&'static str] {
+        &["id", "org_id", "site_id", "tags"]
+    }
+
+    fn records(&self) -> Vec<&OrgSetting> {
+        vec![self]
+    }
+}
+
+pub fn get_setting<'a>(c: &HttpClient, org_id: &'a OrgId) -> Result<OrgSetting, MistError> {
+    match c.get(org_setting_path(c, org_id), &()) {
+        Ok(orgs) => {
             debug("org setting request succeed");
             Ok(orgs)
         }
-        _ => {
-            warn("org setting request failed");
-            Err(())
+        Err(e) => {
+            warn(&format!("org setting request failed: {}", e));
+            Err(e)
         }
     }
 }
 
 pub fn update_setting<'a>(
     c: &HttpClient,
-    org_id: &'a str,
+    org_id: &'a OrgId,
     params: &OrgSettingParams,
-) -> Result<OrgSetting, ()> {
-    match c.put(org_setting_path(org_id), params) {
-        Ok(Some(orgs)) => {
+) -> Result<OrgSetting, MistError> {
+    match c.put(org_setting_path(c, org_id), params) {
+        Ok(orgs) => {
             debug("org setting change request succeed");
             Ok(orgs)
         }
-        _ => {
-            warn("org setting change request failed");
-            Err(())
+        Err(e) => {
+            warn(&format!("org setting change request failed: {}", e));
+            Err(e)
         }
     }
 }
 
-pub fn get_stats<'a>(c: &HttpClient, org_id: &'a str) -> Result<OrgStats, ()> {
-    match c.get(org_stats_path(org_id), &()) {
-        Ok(Some(orgs)) => {
+pub fn get_stats<'a>(c: &HttpClient, org_id: &'a OrgId) -> Result<OrgStats, MistError> {
+    match c.get(org_stats_path(c, org_id), &()) {
+        Ok(orgs) => {
             debug("org stats request succeed");
             Ok(orgs)
         }
-        _ => {
-            warn("org stats request failed");
-            Err(())
+        Err(e) => {
+            warn(&format!("org stats request failed: {}", e));
+            Err(e)
         }
     }
 }
 
-pub fn create(c: &HttpClient, org: &Org) -> Result<Org, ()> {
-    match c.post(orgs_path(), org) {
-        Ok(Some(orgs)) => {
+pub fn create(c: &HttpClient, org: &Org) -> Result<Org, MistError> {
+    match c.post(orgs_path(c), org) {
+        Ok(orgs) => {
             debug("org create request succeed");
             Ok(orgs)
         }
-        _ => {
-            warn("org create request failed");
-            Err(())
+        Err(e) => {
+            warn(&format!("org create request failed: {}", e));
+            Err(e)
         }
     }
 }
 
-pub fn update<'a>(c: &HttpClient, org_id: &'a str, org: &Org) -> Result<Org, ()> {
-    match c.put(org_path(org_id), org) {
-        Ok(Some(orgs)) => {
+pub fn update<'a>(c: &HttpClient, org_id: &'a OrgId, org: &Org) -> Result<Org, MistError> {
+    match c.put(org_path(c, org_id), org) {
+        Ok(orgs) => {
             debug("org create request succeed");
             Ok(orgs)
         }
-        _ => {
-            warn("org create request failed");
-            Err(())
+        Err(e) => {
+            warn(&format!("org create request failed: {}", e));
+            Err(e)
         }
     }
 }
 
-pub fn clone<'a>(c: &HttpClient, org_id: &'a str, name: &'a str) -> Result<Org, ()> {
+/// Creates an org named `name` with a copy of the config of `org_id`,
+/// and returns the ID of the new org
+pub fn clone<'a>(c: &HttpClient, org_id: &'a OrgId, name: &'a str) -> Result<OrgId, MistError> {
     let mut req: HashMap<&str, &str> = HashMap::new();
     req.insert("name", name);
-    match c.put(org_path(org_id), &req) {
-        Ok(Some(orgs)) => {
-            debug("org create request succeed");
-            Ok(orgs)
+    match c.post::<_, Org>(org_clone_path(c, org_id), &req) {
+        Ok(Org { id: Some(id), .. }) => {
+            debug("org clone request succeed");
+            Ok(id)
         }
-        _ => {
-            warn("org create request failed");
-            Err(())
+        Ok(_) => {
+            warn("org clone request succeed without the id of the new org");
+            Err(MistError::Invalid("clone reply without an org id".into()))
+        }
+        Err(e) => {
+            warn(&format!("org clone request failed: {}", e));
+            Err(e)
         }
     }
 }
 
-/// private functions
+// private functions
 
-fn orgs_path() -> String {
-    format!("{}/orgs", MIST_API_BASE)
+fn orgs_path(c: &HttpClient) -> String {
+    c.url("orgs")
 }
 
-fn org_path<'a>(org_id: &'a str) -> String {
-    format!("{}/orgs/{}", MIST_API_BASE, org_id)
+fn org_path<'a>(c: &HttpClient, org_id: &'a OrgId) -> String {
+    c.url(&format!("orgs/{}", org_id))
 }
 
-fn org_stats_path<'a>(org_id: &'a str) -> String {
-    format!("{}/orgs/{}/stats", MIST_API_BASE, org_id)
+fn org_clone_path<'a>(c: &HttpClient, org_id: &'a OrgId) -> String {
+    c.url(&format!("orgs/{}/clone", org_id))
 }
 
-fn org_setting_path<'a>(org_id: &'a str) -> String {
-    format!("{}/orgs/{}/setting", MIST_API_BASE, org_id)
+fn org_stats_path<'a>(c: &HttpClient, org_id: &'a OrgId) -> String {
+    c.url(&format!("orgs/{}/stats", org_id))
 }
 
-fn default_allow_mist() -> bool {
-    true
+pub(crate) fn org_setting_path<'a>(c: &HttpClient, org_id: &'a OrgId) -> String {
+    c.url(&format!("orgs/{}/setting", org_id))
 }
 
-fn default_enabled() -> bool {
+fn default_allow_mist() -> bool {
     true
 }
 
 fn default_disabled() -> bool {
-    true
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn setting_params_send_only_given_fields() {
+        let params: OrgSettingParams = serde_json::from_str(
+            r#"{"ui_idle_timeout": 30, "security": {"fips_zeroize_password": "x"},
+                "installer": {"grace_period": 7, "extra_site_ids": []}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            serde_json::to_value(&params).unwrap(),
+            serde_json::json!({
+                "ui_idle_timeout": 30,
+                "security": {"fips_zeroize_password": "x"},
+                "installer": {"grace_period": 7, "extra_site_ids": []}
+            })
+        );
+    }
+
+    #[test]
+    fn setting_params_keep_given_flags() {
+        let params: OrgSettingParams = serde_json::from_str(
+            r#"{"password_policy": {"freshness": 90, "min_length": 12,
+                "requires_two_factor_auth": false}}"#,
+        )
+        .unwrap();
+        let policy = params.password_policy.unwrap();
+        assert_eq!(policy.requires_two_factor_auth, Some(false));
+        assert_eq!(policy.requires_special_char, None);
+        assert_eq!(policy.enabled, None);
+    }
 }
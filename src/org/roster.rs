@@ -0,0 +1,420 @@
+// Copyright 2020 Eishun Kondoh
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::auth::login;
+use crate::auth::tokens::TokenPrivilege;
+use crate::error::MistError;
+use crate::http::HttpClient;
+use crate::logger::{info, warn};
+use crate::org::admins::{self, Admin, Admins, Invite, InviteParams, Invites};
+use crate::output::Render;
+use crate::types::{OrgId, Role, Scope, SiteId};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The people who should be admins of an org, kept in a YAML file:
+///
+/// ```yaml
+/// admins:
+///   - email: alice@example.com
+///     first_name: Alice
+///     last_name: Smith
+///     privileges:
+///       - {scope: org, role: admin}
+///       - {scope: site, role: write, site: Tokyo}
+/// ```
+///
+/// `site` is a site ID or name; sitegroup privileges take a
+/// `sitegroup_id`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Roster {
+    pub admins: Vec<RosterAdmin>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RosterAdmin {
+    pub email: String,
+    #[serde(default)]
+    pub first_name: String,
+    #[serde(default)]
+    pub last_name: String,
+    pub privileges: Vec<RosterPrivilege>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RosterPrivilege {
+    pub scope: Scope,
+    pub role: Role,
+    #[serde(default)]
+    pub site: Option<String>,
+    #[serde(default)]
+    pub sitegroup_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct RosterChanges(Vec<RosterChange>);
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RosterChange {
+    pub email: String,
+    pub action: RosterAction,
+    // false for dry runs, skipped admins and failed requests
+    pub done: bool,
+    #[serde(default)]
+    pub detail: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RosterAction {
+    Invite,
+    Revoke,
+    // a pending invite of a leaver, deleted
+    Uninvite,
+    // a leaver which can not be revoked here
+    Skip,
+}
+
+impl RosterChanges {
+    /// number of invites and revocations which failed
+    pub fn failed(&self) -> usize {
+        self.0
+            .iter()
+            .filter(|change| change.action != RosterAction::Skip && change.detail.is_some())
+            .count()
+    }
+}
+
+impl From<Vec<RosterChange>> for RosterChanges {
+    fn from(v: Vec<RosterChange>) -> Self {
+        RosterChanges(v)
+    }
+}
+
+impl Render for RosterChanges {
+    type Record = RosterChange;
+
+    fn default_columns() -> &'static [&'static str] {
+        &["email", "action", "done", "detail"]
+    }
+
+    fn records(&self) -> Vec<&RosterChange> {
+        self.0.iter().collect()
+    }
+}
+
+pub fn read_roster<'a>(path: &'a Path) -> Result<Roster, MistError> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| MistError::Config(format!("{}: {}", path.display(), e)))?;
+    serde_yaml::from_str(&content)
+        .map_err(|e| MistError::Invalid(format!("{}: {}", path.display(), e)))
+}
+
+/// Invites the people of `roster` who are neither admins of the org nor
+/// invited yet, deleting their expired invites first, revokes the admins
+/// missing from it and deletes their pending invites. Emails are compared regardless of case. The current
+/// user and admins whose privileges come from an MSP or org group are
+/// never revoked.
+///
+/// With `dry_run` only the changes are listed. Site names of the roster
+/// are resolved with `site_id` before anything is changed.
+pub fn sync<'a, F>(
+    c: &HttpClient,
+    org_id: &'a OrgId,
+    roster: &'a Roster,
+    dry_run: bool,
+    mut site_id: F,
+) -> Result<RosterChanges, MistError>
+where
+    F: FnMut(&str) -> Result<SiteId, MistError>,
+{
+    // an empty roster would revoke everyone
+    if roster.admins.is_empty() {
+        return Err(MistError::Invalid("the roster has no admins".into()));
+    }
+    let mut invites = Vec::new();
+    for admin in roster.admins.iter() {
+        let mut privileges = Vec::new();
+        for p in admin.privileges.iter() {
+            let privilege = token_privilege(p, &mut site_id)
+                .map_err(|e| MistError::Invalid(format!("{}: {}", admin.email, e)))?;
+            privileges.push(privilege);
+        }
+        invites.push(InviteParams {
+            email: admin.email.clone(),
+            first_name: admin.first_name.clone(),
+            last_name: admin.last_name.clone(),
+            hours: None,
+            privileges,
+        });
+    }
+
+    let current = admins::list(c, org_id)?;
+    let listed = admins::list_invites(c, org_id)?;
+    let me = login::get_priviledges(c)?.email;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let mut changes = Vec::new();
+    for step in plan(roster, &current, &listed, &me, now) {
+        let (email, action) = match &step {
+            Step::Invite(index, _) => (&invites[*index].email, RosterAction::Invite),
+            Step::Revoke(admin) => (&admin.email, RosterAction::Revoke),
+            Step::Uninvite(invite) => (&invite.email, RosterAction::Uninvite),
+            Step::Skip(admin, _) => (&admin.email, RosterAction::Skip),
+        };
+        let result = match step {
+            Step::Skip(_, reason) => Err(reason.to_string()),
+            _ if dry_run => Ok(false),
+            Step::Invite(index, expired) => expired
+                .iter()
+                .try_for_each(|invite_id| admins::delete_invite(c, org_id, invite_id))
+                .and_then(|_| admins::invite(c, org_id, &invites[index]))
+                .map(|_| true)
+                .map_err(|e| e.to_string()),
+            Step::Revoke(admin) => admins::revoke(c, org_id, &admin.admin_id)
+                .map(|_| true)
+                .map_err(|e| e.to_string()),
+            Step::Uninvite(invite) => admins::delete_invite(c, org_id, &invite.id)
+                .map(|_| true)
+                .map_err(|e| e.to_string()),
+        };
+        changes.push(RosterChange {
+            email: email.clone(),
+            action,
+            done: result == Ok(true),
+            detail: result.err(),
+        });
+    }
+    let changes = RosterChanges(changes);
+    if changes.failed() > 0 {
+        warn(&format!("{} roster changes failed", changes.failed()));
+    } else {
+        info(&format!("org {} in sync with the roster", org_id));
+    }
+    Ok(changes)
+}
+
+// private functions
+
+// what sync changes, decided from the admins and invites of the org
+#[derive(Debug)]
+enum Step<'a> {
+    // the roster admin at this index, after deleting their expired invites
+    Invite(usize, Vec<&'a str>),
+    Revoke(&'a Admin),
+    // a pending invite of a leaver
+    Uninvite(&'a Invite),
+    Skip(&'a Admin, &'static str),
+}
+
+fn plan<'a>(
+    roster: &'a Roster,
+    current: &'a Admins,
+    listed: &'a Invites,
+    me: &'a str,
+    now: u64,
+) -> Vec<Step<'a>> {
+    let mut steps = Vec::new();
+    for (index, admin) in roster.admins.iter().enumerate() {
+        let email = &admin.email;
+        if current.iter().any(|a| a.email.eq_ignore_ascii_case(email)) {
+            continue;
+        }
+        let invites: Vec<&Invite> = listed
+            .iter()
+            .filter(|invite| invite.email.eq_ignore_ascii_case(email))
+            .collect();
+        // expired invites can not be accepted, so those people are invited again
+        if invites
+            .iter()
+            .any(|invite| invite.expire_time.is_none_or(|t| t > now))
+        {
+            continue;
+        }
+        let expired = invites.iter().map(|invite| invite.id.as_str()).collect();
+        steps.push(Step::Invite(index, expired));
+    }
+    let leaves = |email: &str| {
+        !roster
+            .admins
+            .iter()
+            .any(|a| a.email.eq_ignore_ascii_case(email))
+    };
+    for admin in current.iter().filter(|admin| leaves(&admin.email)) {
+        let own = admin
+            .privileges
+            .iter()
+            .any(|p| matches!(p.scope, Scope::Org | Scope::Site | Scope::Sitegroup));
+        steps.push(if admin.email.eq_ignore_ascii_case(me) {
+            Step::Skip(admin, "the current user is not revoked")
+        } else if !own {
+            Step::Skip(admin, "privileges of an MSP or org group")
+        } else {
+            Step::Revoke(admin)
+        });
+    }
+    for invite in listed.iter().filter(|invite| leaves(&invite.email)) {
+        steps.push(Step::Uninvite(invite));
+    }
+    steps
+}
+
+fn token_privilege<'a, F>(
+    p: &'a RosterPrivilege,
+    site_id: &mut F,
+) -> Result<TokenPrivilege, MistError>
+where
+    F: FnMut(&str) -> Result<SiteId, MistError>,
+{
+    let mut privilege = TokenPrivilege {
+        scope: p.scope.clone(),
+        role: p.role.clone(),
+        site_id: None,
+        sitegroup_id: None,
+    };
+    match (&p.scope, &p.site, &p.sitegroup_id) {
+        (Scope::Org, None, None) => {}
+        (Scope::Site, Some(site), None) => privilege.site_id = Some(site_id(site)?),
+        (Scope::Sitegroup, None, Some(id)) => privilege.sitegroup_id = Some(id.clone()),
+        _ => {
+            return Err(MistError::Invalid(format!(
+                "invalid {} privilege: org takes no site, site a site and sitegroup a sitegroup_id",
+                p.scope
+            )))
+        }
+    }
+    Ok(privilege)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const SITE: &str = "978c48e6-6ef6-11e6-8bbf-02e208b2d34f";
+
+    fn roster(emails: &[&str]) -> Roster {
+        Roster {
+            admins: emails
+                .iter()
+                .map(|email| RosterAdmin {
+                    email: email.to_string(),
+                    first_name: String::new(),
+                    last_name: String::new(),
+                    privileges: Vec::new(),
+                })
+                .collect(),
+        }
+    }
+
+    fn admins(admins: serde_json::Value) -> Admins {
+        serde_json::from_value::<Vec<Admin>>(admins).unwrap().into()
+    }
+
+    fn invites(invites: serde_json::Value) -> Invites {
+        serde_json::from_value::<Vec<Invite>>(invites)
+            .unwrap()
+            .into()
+    }
+
+    fn privilege(yaml: &str) -> Result<TokenPrivilege, MistError> {
+        let p: RosterPrivilege = serde_yaml::from_str(yaml).unwrap();
+        token_privilege(&p, &mut |site: &str| match site {
+            "Tokyo" => SITE.parse(),
+            _ => Err(MistError::Invalid(format!("no site named {}", site))),
+        })
+    }
+
+    #[test]
+    fn privileges_map_to_their_scope() {
+        let p = privilege("{scope: org, role: admin}").unwrap();
+        assert_eq!((p.scope, p.role), (Scope::Org, Role::Admin));
+        assert!(p.site_id.is_none() && p.sitegroup_id.is_none());
+
+        let p = privilege("{scope: site, role: write, site: Tokyo}").unwrap();
+        assert_eq!((p.scope, p.role), (Scope::Site, Role::Write));
+        assert_eq!(p.site_id.unwrap().to_string(), SITE);
+
+        let p = privilege("{scope: sitegroup, role: read, sitegroup_id: g1}").unwrap();
+        assert_eq!((p.scope, p.role), (Scope::Sitegroup, Role::Read));
+        assert_eq!(p.sitegroup_id.as_deref(), Some("g1"));
+    }
+
+    #[test]
+    fn privileges_need_the_target_of_their_scope() {
+        assert!(privilege("{scope: org, role: admin, site: Tokyo}").is_err());
+        assert!(privilege("{scope: site, role: write}").is_err());
+        assert!(privilege("{scope: site, role: write, sitegroup_id: g1}").is_err());
+        assert!(privilege("{scope: sitegroup, role: read}").is_err());
+        assert!(privilege("{scope: site, role: write, site: Osaka}").is_err());
+        assert!(privilege("{scope: msp, role: admin}").is_err());
+    }
+
+    #[test]
+    fn invites_people_missing_from_the_org() {
+        let roster = roster(&["me@x", "Bob@x", "carol@x", "dave@x"]);
+        let current = admins(json!([
+            {"admin_id": "a1", "email": "me@x", "privileges": [{"scope": "org", "role": "admin"}]},
+        ]));
+        let listed = invites(json!([
+            {"id": "i1", "email": "bob@x"},
+            {"id": "i2", "email": "carol@x", "expire_time": 2000},
+            {"id": "i3", "email": "dave@x", "expire_time": 500},
+            {"id": "i4", "email": "dave@x", "expire_time": 900},
+        ]));
+        let steps = plan(&roster, &current, &listed, "me@x", 1000);
+        // bob and carol have a pending invite, dave's have expired
+        match steps.as_slice() {
+            [Step::Invite(3, expired)] => assert_eq!(expired, &["i3", "i4"]),
+            _ => panic!("{:?}", steps),
+        }
+    }
+
+    #[test]
+    fn revokes_and_uninvites_leavers() {
+        let roster = roster(&["me@x"]);
+        let current = admins(json!([
+            {"admin_id": "a1", "email": "ME@x", "privileges": [{"scope": "org", "role": "admin"}]},
+            {"admin_id": "a2", "email": "gone@x", "privileges": [{"scope": "site", "role": "read"}]},
+            {"admin_id": "a3", "email": "msp@x", "privileges": [{"scope": "msp", "role": "admin"}]},
+        ]));
+        let listed = invites(json!([
+            {"id": "i1", "email": "leaver@x", "expire_time": 2000},
+            {"id": "i2", "email": "old@x", "expire_time": 500},
+            {"id": "i3", "email": "Me@x"},
+        ]));
+        let steps = plan(&roster, &current, &listed, "me@x", 1000);
+        match steps.as_slice() {
+            [Step::Revoke(gone), Step::Skip(msp, reason), Step::Uninvite(leaver), Step::Uninvite(old)] =>
+            {
+                assert_eq!(gone.admin_id, "a2");
+                assert_eq!(msp.admin_id, "a3");
+                assert_eq!(*reason, "privileges of an MSP or org group");
+                assert_eq!((leaver.id.as_str(), old.id.as_str()), ("i1", "i2"));
+            }
+            _ => panic!("{:?}", steps),
+        }
+    }
+
+    #[test]
+    fn never_revokes_the_current_user() {
+        let roster = roster(&["alice@x"]);
+        let current = admins(json!([
+            {"admin_id": "a1", "email": "alice@x", "privileges": [{"scope": "org", "role": "admin"}]},
+            {"admin_id": "a2", "email": "Me@x", "privileges": [{"scope": "org", "role": "admin"}]},
+        ]));
+        let listed = invites(json!([]));
+        let steps = plan(&roster, &current, &listed, "me@x", 1000);
+        match steps.as_slice() {
+            [Step::Skip(me, reason)] => {
+                assert_eq!(me.admin_id, "a2");
+                assert_eq!(*reason, "the current user is not revoked");
+            }
+            _ => panic!("{:?}", steps),
+        }
+    }
+}
@@ -0,0 +1,187 @@
+// Copyright 2020 Eishun Kondoh
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::error::MistError;
+use crate::http::HttpClient;
+use crate::logger::{debug, info, warn};
+use crate::org::org;
+use crate::output::Render;
+use crate::sites::sites;
+use crate::types::OrgId;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeSet;
+
+/// template listings of an org compared by name
+const TEMPLATES: [&str; 6] = [
+    "templates",
+    "rftemplates",
+    "networktemplates",
+    "gatewaytemplates",
+    "alarmtemplates",
+    "sitetemplates",
+];
+
+/// setting fields which differ between any two orgs, besides the IDs
+const OWN_SETTING_FIELDS: [&str; 3] = ["name", "created_time", "modified_time"];
+
+/// The clone of an org and what of its source did not carry over
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CloneReport {
+    pub org_id: OrgId,
+    pub source_org_id: OrgId,
+    pub diffs: Vec<CloneDiff>,
+}
+
+/// Something of the source org which did not carry over to the clone
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CloneDiff {
+    // setting, sitegroups, templates, rftemplates, ...
+    pub kind: String,
+    // setting field, or name of the site group or template
+    pub name: String,
+    // missing or differs
+    pub detail: String,
+}
+
+impl CloneReport {
+    pub fn is_empty(&self) -> bool {
+        self.diffs.is_empty()
+    }
+}
+
+impl Render for CloneReport {
+    type Record = CloneDiff;
+
+    fn default_columns() -> &'static [&'static str] {
+        &["kind", "name", "detail"]
+    }
+
+    fn records(&self) -> Vec<&CloneDiff> {
+        self.diffs.iter().collect()
+    }
+}
+
+/// Compares the settings, site groups and templates of `source` with
+/// those of its clone `copy`. Site groups and templates are matched by
+/// name, settings field by field leaving out the name and the IDs.
+pub fn compare_clone<'a>(
+    c: &HttpClient,
+    source: &'a OrgId,
+    copy: &'a OrgId,
+) -> Result<CloneReport, MistError> {
+    let mut diffs = Vec::new();
+
+    let (from, to) = (setting(c, source)?, setting(c, copy)?);
+    if let (Value::Object(from), Value::Object(to)) = (&from, &to) {
+        for (field, value) in from {
+            if OWN_SETTING_FIELDS.contains(&field.as_str()) || is_id_field(field) {
+                continue;
+            }
+            let detail = match to.get(field) {
+                None => "missing",
+                Some(copied) if without_ids(copied) != without_ids(value) => "differs",
+                Some(_) => continue,
+            };
+            diffs.push(diff("setting", field, detail));
+        }
+    }
+
+    let group_names = |org_id| -> Result<BTreeSet<String>, MistError> {
+        Ok(sites::list_group_all(c, org_id)?
+            .iter()
+            .map(|group| group.name.clone())
+            .collect())
+    };
+    let copied = group_names(copy)?;
+    for name in group_names(source)?.difference(&copied) {
+        diffs.push(diff("sitegroups", name, "missing"));
+    }
+
+    for kind in TEMPLATES.iter() {
+        let copied = template_names(c, copy, kind)?;
+        for name in template_names(c, source, kind)?.difference(&copied) {
+            diffs.push(diff(kind, name, "missing"));
+        }
+    }
+
+    if diffs.is_empty() {
+        info(&format!("org {} matches its source {}", copy, source));
+    } else {
+        warn(&format!(
+            "{} items of org {} did not carry over to {}",
+            diffs.len(),
+            source,
+            copy
+        ));
+    }
+    Ok(CloneReport {
+        org_id: copy.clone(),
+        source_org_id: source.clone(),
+        diffs,
+    })
+}
+
+// private functions
+
+fn diff<'a>(kind: &'a str, name: &'a str, detail: &'a str) -> CloneDiff {
+    CloneDiff {
+        kind: kind.to_string(),
+        name: name.to_string(),
+        detail: detail.to_string(),
+    }
+}
+
+// the whole setting, most of which has no type in org::OrgSetting
+fn setting<'a>(c: &HttpClient, org_id: &'a OrgId) -> Result<Value, MistError> {
+    match c.get(org::org_setting_path(c, org_id), &()) {
+        Ok(setting) => {
+            debug("org setting request succeed");
+            Ok(setting)
+        }
+        Err(e) => {
+            warn(&format!("org setting request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+// the clone has IDs of its own for the org and whatever it references,
+// e.g. templates, so those are left out of the comparison
+fn is_id_field(field: &str) -> bool {
+    field == "id" || field.ends_with("_id") || field.ends_with("_ids")
+}
+
+fn without_ids(value: &Value) -> Value {
+    match value {
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .filter(|(field, _)| !is_id_field(field))
+                .map(|(field, value)| (field.clone(), without_ids(value)))
+                .collect(),
+        ),
+        Value::Array(values) => Value::Array(values.iter().map(without_ids).collect()),
+        _ => value.clone(),
+    }
+}
+
+fn template_names<'a>(
+    c: &HttpClient,
+    org_id: &'a OrgId,
+    kind: &'a str,
+) -> Result<BTreeSet<String>, MistError> {
+    match c.get_all::<Value>(c.url(&format!("orgs/{}/{}", org_id, kind))) {
+        Ok(templates) => {
+            debug(&format!("list {} request succeed", kind));
+            Ok(templates
+                .iter()
+                .filter_map(|t| t["name"].as_str().map(str::to_string))
+                .collect())
+        }
+        Err(e) => {
+            warn(&format!("list {} request failed: {}", kind, e));
+            Err(e)
+        }
+    }
+}
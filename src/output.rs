@@ -0,0 +1,168 @@
+// Copyright 2020 Eishun Kondoh
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::error::MistError;
+use prettytable::{format, Cell, Row, Table};
+use serde::Serialize;
+use serde_json::Value;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    Json,
+    Yaml,
+    Csv,
+}
+
+impl FromStr for OutputFormat {
+    type Err = MistError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match &*s.to_ascii_lowercase() {
+            "table" => Ok(OutputFormat::Table),
+            "json" => Ok(OutputFormat::Json),
+            "yaml" | "yml" => Ok(OutputFormat::Yaml),
+            "csv" => Ok(OutputFormat::Csv),
+            _ => Err(MistError::Config(format!("unknown output format: {}", s))),
+        }
+    }
+}
+
+/// A result that can be printed as a table or CSV, one row per record.
+/// JSON and YAML print the value itself.
+///
+/// Any top-level field of a record can be selected as a column,
+/// `default_columns` is used when no columns are given.
+pub trait Render: Serialize {
+    type Record: Serialize;
+
+    fn default_columns() -> &'static [&'static str];
+
+    fn records(&self) -> Vec<&Self::Record>;
+}
+
+pub fn render<'a, R, W>(
+    w: &mut W,
+    value: &R,
+    fmt: OutputFormat,
+    columns: Option<&'a [String]>,
+) -> Result<(), MistError>
+where
+    R: Render,
+    W: Write,
+{
+    match fmt {
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut *w, value).map_err(io::Error::from)?;
+            writeln!(w)?;
+        }
+        OutputFormat::Yaml => {
+            serde_yaml::to_writer(&mut *w, value).map_err(io::Error::other)?;
+            writeln!(w)?;
+        }
+        OutputFormat::Table => {
+            let columns = columns_of::<R>(columns);
+            let mut table = Table::new();
+            table.set_format(*format::consts::FORMAT_CLEAN);
+            table.set_titles(Row::new(
+                columns
+                    .iter()
+                    .map(|c| Cell::new(&c.to_uppercase()))
+                    .collect(),
+            ));
+            for record in value.records() {
+                let cells = cells(record, &columns)?;
+                table.add_row(Row::new(cells.iter().map(|c| Cell::new(c)).collect()));
+            }
+            table.print(w)?;
+        }
+        OutputFormat::Csv => {
+            let mut stream = CsvStream::new(w, columns_of::<R>(columns));
+            stream.write(value)?;
+            stream.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes CSV rows as results arrive, so that large listings are never
+/// held in a table. The header is written before the first rows.
+pub struct CsvStream<W: Write> {
+    w: csv::Writer<W>,
+    columns: Vec<String>,
+    header: bool,
+}
+
+impl<W: Write> CsvStream<W> {
+    pub fn new(w: W, columns: Vec<String>) -> Self {
+        CsvStream {
+            w: csv::Writer::from_writer(w),
+            columns,
+            header: false,
+        }
+    }
+
+    pub fn for_records<'a, R: Render>(w: W, columns: Option<&'a [String]>) -> Self {
+        CsvStream::new(w, columns_of::<R>(columns))
+    }
+
+    pub fn write<R: Render>(&mut self, value: &R) -> Result<(), MistError> {
+        for record in value.records() {
+            self.write_record(record)?;
+        }
+        Ok(())
+    }
+
+    pub fn write_record<T: Serialize>(&mut self, record: &T) -> Result<(), MistError> {
+        if !self.header {
+            self.w
+                .write_record(&self.columns)
+                .map_err(io::Error::from)?;
+            self.header = true;
+        }
+        let cells = cells(record, &self.columns)?;
+        self.w.write_record(&cells).map_err(io::Error::from)?;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), MistError> {
+        if !self.header {
+            self.w
+                .write_record(&self.columns)
+                .map_err(io::Error::from)?;
+            self.header = true;
+        }
+        self.w.flush()?;
+        Ok(())
+    }
+}
+
+// private functions
+
+fn columns_of<'a, R: Render>(columns: Option<&'a [String]>) -> Vec<String> {
+    match columns {
+        Some(columns) if !columns.is_empty() => columns.to_vec(),
+        _ => R::default_columns().iter().map(|c| c.to_string()).collect(),
+    }
+}
+
+fn cells<'a, T: Serialize>(record: &T, columns: &'a [String]) -> Result<Vec<String>, MistError> {
+    let value = serde_json::to_value(record).map_err(io::Error::from)?;
+    Ok(columns
+        .iter()
+        .map(|column| value.get(column).map(cell).unwrap_or_default())
+        .collect())
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(values) => values.iter().map(cell).collect::<Vec<_>>().join(","),
+        Value::Object(_) => value.to_string(),
+        _ => value.to_string(),
+    }
+}
@@ -0,0 +1,414 @@
+// Copyright 2020 Eishun Kondoh
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::auth::login::{self, Privilege};
+use crate::config::write_private;
+use crate::error::MistError;
+use crate::http::HttpClient;
+use crate::logger::debug;
+use crate::sites::sites;
+use crate::types::{is_uuid, MspId, OrgId, Scope, SiteId};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// default lifetime of the cached privileges and site names
+pub const DEFAULT_TTL: Duration = Duration::from_secs(3600);
+
+/// Resolves org and site names to IDs with the privileges of the
+/// current user and the sites of its orgs, and checks that the user may
+/// change an org or site before a write is sent.
+///
+/// Privileges and site lists are cached, in `path` when given, for `ttl`.
+#[derive(Debug)]
+pub struct Resolver {
+    cache: Cache,
+    path: Option<PathBuf>,
+    ttl: Duration,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Cache {
+    base: String,
+    // epoch seconds at which the privileges were fetched
+    #[serde(default)]
+    fetched: u64,
+    #[serde(default)]
+    privileges: Vec<Privilege>,
+    // sites by org_id, with the epoch seconds they were fetched at
+    #[serde(default)]
+    sites: BTreeMap<OrgId, SiteNames>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct SiteNames {
+    fetched: u64,
+    sites: Vec<(SiteId, String)>,
+}
+
+impl Resolver {
+    /// Resolver for the API of `base`, caching in `path` when given
+    pub fn new<'a>(base: &'a str, path: Option<PathBuf>, ttl: Duration) -> Resolver {
+        let cache = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str::<Cache>(&content).ok())
+            .filter(|cache| cache.base == base)
+            .unwrap_or_else(|| Cache {
+                base: base.to_string(),
+                ..Default::default()
+            });
+        Resolver { cache, path, ttl }
+    }
+
+    /// ID of an org given by ID or by the name of one of its privileges
+    pub fn org_id<'a>(&mut self, c: &HttpClient, org: &'a str) -> Result<OrgId, MistError> {
+        if is_uuid(org) {
+            return org.parse();
+        }
+        let privileges = self.privileges(c)?;
+        let mut found: Vec<(OrgId, String)> = privileges
+            .iter()
+            .filter_map(|p| {
+                let name = match p.scope {
+                    Scope::Org => Some(&p.name),
+                    _ => p.org_name.as_ref(),
+                }?;
+                Some((p.org_id.clone()?, name.clone()))
+            })
+            .collect();
+        found.sort();
+        found.dedup();
+        let id = pick("org", org, &found)?;
+        debug(&format!("org {} resolved to {}", org, id));
+        Ok(id)
+    }
+
+    /// ID of an MSP given by ID or by name, or the only MSP of the
+    /// privileges when none is given
+    pub fn msp_id<'a>(&mut self, c: &HttpClient, msp: Option<&'a str>) -> Result<MspId, MistError> {
+        if let Some(msp) = msp.filter(|msp| is_uuid(msp)) {
+            return msp.parse();
+        }
+        let privileges = self.privileges(c)?;
+        let mut found: Vec<(MspId, String)> = privileges
+            .iter()
+            .filter_map(|p| {
+                let id = p.msp_id.clone()?;
+                let name = match p.scope {
+                    Scope::Msp => Some(&p.name),
+                    _ => p.msp_name.as_ref(),
+                };
+                Some((id, name.cloned().unwrap_or_default()))
+            })
+            .collect();
+        // org privileges may carry the msp_id alone, keep the named entry
+        found.sort_by(|a, b| b.cmp(a));
+        found.dedup_by(|a, b| a.0 == b.0);
+        let id = match (msp, found.as_slice()) {
+            (Some(msp), _) => pick("msp", msp, &found)?,
+            (None, [(id, _)]) => id.clone(),
+            (None, []) => return Err(MistError::Invalid("no msp among your privileges".into())),
+            (None, _) => {
+                return Err(MistError::Config(
+                    "you have privileges on several MSPs, use --msp".into(),
+                ))
+            }
+        };
+        debug(&format!("msp resolved to {}", id));
+        Ok(id)
+    }
+
+    /// ID of a site given by ID or by name. The name is looked up in the
+    /// sites of `org_id` when given, otherwise in the sites of every org
+    /// the user has a privilege on.
+    pub fn site_id<'a>(
+        &mut self,
+        c: &HttpClient,
+        org_id: Option<&'a OrgId>,
+        site: &'a str,
+    ) -> Result<SiteId, MistError> {
+        if is_uuid(site) {
+            return site.parse();
+        }
+        let mut found = self.site_names(c, org_id, false)?;
+        // a site missing from the cache may have been created since
+        if !found.iter().any(|(_, name)| name == site) {
+            found = self.site_names(c, org_id, true)?;
+        }
+        let id = pick("site", site, &found)?;
+        debug(&format!("site {} resolved to {}", site, id));
+        Ok(id)
+    }
+
+    /// Refuses a write on an MSP, org or site when every privilege of the
+    /// user on it has a read-only role. Targets without a matching
+    /// privilege are left to the API to refuse.
+    pub fn check_write<'a>(
+        &mut self,
+        c: &HttpClient,
+        msp_id: Option<&'a MspId>,
+        org_id: Option<&'a OrgId>,
+        site_id: Option<&'a SiteId>,
+    ) -> Result<(), MistError> {
+        // the org of a site is known once its org's sites were listed
+        let site_org = site_id.and_then(|site_id| {
+            self.cache
+                .sites
+                .iter()
+                .find(|(_, names)| names.sites.iter().any(|(id, _)| id == site_id))
+                .map(|(org_id, _)| org_id.clone())
+        });
+        let org_id = org_id.cloned().or(site_org);
+        let privileges = self.privileges(c)?;
+        let roles: Vec<&Privilege> = privileges
+            .iter()
+            .filter(|p| match p.scope {
+                Scope::Msp => msp_id.is_some() && msp_id == p.msp_id.as_ref(),
+                Scope::Org => org_id.is_some() && org_id == p.org_id,
+                Scope::Site => site_id.is_some() && site_id == p.site_id.as_ref(),
+                _ => false,
+            })
+            .collect();
+        match roles.first() {
+            Some(p) if roles.iter().all(|p| p.role.is_read_only()) => {
+                Err(MistError::auth(&format!(
+                    "the {} role on {} {} does not allow changes",
+                    p.role, p.scope, p.name
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // private functions
+
+    fn privileges(&mut self, c: &HttpClient) -> Result<Vec<Privilege>, MistError> {
+        if self.is_stale(self.cache.fetched) {
+            self.cache.privileges = login::get_priviledges(c)?.privileges;
+            self.cache.fetched = now();
+            self.save()?;
+        }
+        Ok(self.cache.privileges.clone())
+    }
+
+    fn site_names<'a>(
+        &mut self,
+        c: &HttpClient,
+        org_id: Option<&'a OrgId>,
+        refresh: bool,
+    ) -> Result<Vec<(SiteId, String)>, MistError> {
+        let privileges = self.privileges(c)?;
+        let mut found = Vec::new();
+        // sites the user only has a site privilege on can not be listed
+        for p in privileges.iter().filter(|p| p.scope == Scope::Site) {
+            if let Some(site_id) = &p.site_id {
+                if org_id.is_none_or(|org_id| p.org_id.as_ref() == Some(org_id)) {
+                    found.push((site_id.clone(), p.name.clone()));
+                }
+            }
+        }
+        let mut orgs: Vec<OrgId> = match org_id {
+            Some(org_id) => vec![org_id.clone()],
+            None => privileges
+                .iter()
+                .filter(|p| p.scope == Scope::Org)
+                .filter_map(|p| p.org_id.clone())
+                .collect(),
+        };
+        orgs.sort();
+        orgs.dedup();
+        let mut changed = false;
+        for org_id in orgs {
+            let cached = self.cache.sites.get(&org_id);
+            if refresh || cached.is_none_or(|names| self.is_stale(names.fetched)) {
+                let sites = sites::list_all(c, &org_id)?
+                    .iter()
+                    .filter_map(|site| Some((site.id.clone()?, site.name.clone())))
+                    .collect();
+                let names = SiteNames {
+                    fetched: now(),
+                    sites,
+                };
+                self.cache.sites.insert(org_id.clone(), names);
+                changed = true;
+            }
+            found.extend(self.cache.sites[&org_id].sites.iter().cloned());
+        }
+        if changed {
+            self.save()?;
+        }
+        found.sort();
+        found.dedup();
+        Ok(found)
+    }
+
+    fn is_stale(&self, fetched: u64) -> bool {
+        now().saturating_sub(fetched) >= self.ttl.as_secs()
+    }
+
+    fn save(&self) -> Result<(), MistError> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let content =
+            serde_json::to_string(&self.cache).map_err(|e| MistError::Invalid(e.to_string()))?;
+        let tmp = path.with_extension("tmp");
+        let _ = fs::remove_file(&tmp);
+        write_private(&tmp, &content)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+// private functions
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::from_secs(0))
+        .as_secs()
+}
+
+// the one id named `name`, exact matches win over case-insensitive ones
+fn pick<'a, T>(kind: &'a str, name: &'a str, found: &'a [(T, String)]) -> Result<T, MistError>
+where
+    T: Clone + Ord + fmt::Display,
+{
+    let mut ids: Vec<&T> = found
+        .iter()
+        .filter(|(_, n)| n == name)
+        .map(|(id, _)| id)
+        .collect();
+    if ids.is_empty() {
+        ids = found
+            .iter()
+            .filter(|(_, n)| n.eq_ignore_ascii_case(name))
+            .map(|(id, _)| id)
+            .collect();
+    }
+    ids.sort();
+    ids.dedup();
+    match ids.as_slice() {
+        [id] => Ok((*id).clone()),
+        [] => Err(MistError::Invalid(format!(
+            "no {} named {} among your privileges",
+            kind, name
+        ))),
+        _ => Err(MistError::Invalid(format!(
+            "{} name {} is ambiguous, use one of the ids: {}",
+            kind,
+            name,
+            ids.iter()
+                .map(|id| id.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::retry::RetryPolicy;
+
+    const ORG_A: &str = "6374a757-ad2c-4b7d-8a5f-1f8dd6a9e9f1";
+    const ORG_B: &str = "8e5d0a5e-5a3b-4a9c-9d3e-2c4e7f3b1a22";
+    const SITE: &str = "978c48e6-6ef6-11e6-8bbf-02e208b2d34f";
+
+    // requests to the client fail at once, resolution must not send any
+    fn client() -> HttpClient {
+        HttpClient::builder()
+            .base_url("http://127.0.0.1:9")
+            .token("token")
+            .retry(RetryPolicy::none())
+            .build()
+            .unwrap()
+    }
+
+    fn resolver(privileges: serde_json::Value) -> Resolver {
+        let mut resolver = Resolver::new("http://127.0.0.1:9", None, DEFAULT_TTL);
+        resolver.cache.privileges = serde_json::from_value(privileges).unwrap();
+        resolver.cache.fetched = now();
+        resolver
+    }
+
+    #[test]
+    fn ids_pass_through_without_requests() {
+        let c = client();
+        let mut resolver = Resolver::new("http://127.0.0.1:9", None, DEFAULT_TTL);
+        assert_eq!(resolver.org_id(&c, ORG_A).unwrap().to_string(), ORG_A);
+        assert_eq!(resolver.site_id(&c, None, SITE).unwrap().to_string(), SITE);
+        let msp_id = resolver.msp_id(&c, Some(ORG_B)).unwrap();
+        assert_eq!(msp_id.to_string(), ORG_B);
+        // a name needs the privileges, which can not be fetched here
+        assert!(resolver.org_id(&c, "Lab").is_err());
+    }
+
+    #[test]
+    fn org_names_resolve_to_their_id() {
+        let c = client();
+        let mut resolver = resolver(serde_json::json!([
+            {"scope": "org", "role": "admin", "org_id": ORG_A, "name": "Lab"},
+            {"scope": "site", "role": "write", "org_id": ORG_B, "org_name": "Shop",
+             "site_id": SITE, "name": "Tokyo"},
+        ]));
+        assert_eq!(resolver.org_id(&c, "Lab").unwrap().to_string(), ORG_A);
+        assert_eq!(resolver.org_id(&c, "shop").unwrap().to_string(), ORG_B);
+        assert!(resolver.org_id(&c, "Home").is_err());
+    }
+
+    #[test]
+    fn ambiguous_names_list_the_candidates() {
+        let c = client();
+        let mut resolver = resolver(serde_json::json!([
+            {"scope": "org", "role": "admin", "org_id": ORG_B, "name": "Lab"},
+            {"scope": "org", "role": "read", "org_id": ORG_A, "name": "Lab"},
+            {"scope": "site", "role": "read", "org_id": ORG_A, "org_name": "Lab",
+             "site_id": SITE, "name": "Tokyo"},
+        ]));
+        let e = resolver.org_id(&c, "Lab").unwrap_err().to_string();
+        assert!(
+            e.contains(&format!(
+                "org name Lab is ambiguous, use one of the ids: {}, {}",
+                ORG_A, ORG_B
+            )),
+            "{}",
+            e
+        );
+    }
+
+    #[test]
+    fn exact_names_win_over_case_insensitive_ones() {
+        let found = vec![(1, "lab".to_string()), (2, "Lab".to_string())];
+        assert_eq!(pick("org", "Lab", &found).unwrap(), 2);
+        assert_eq!(pick("org", "lab", &found).unwrap(), 1);
+        assert!(pick("org", "LAB", &found).is_err());
+        // the same id under several privileges is no ambiguity
+        let found = vec![
+            (1, "Lab".to_string()),
+            (2, "Home".to_string()),
+            (1, "LAB".to_string()),
+        ];
+        assert_eq!(pick("org", "lab", &found).unwrap(), 1);
+    }
+
+    #[test]
+    fn read_only_roles_refuse_writes() {
+        let c = client();
+        let mut resolver = resolver(serde_json::json!([
+            {"scope": "org", "role": "read", "org_id": ORG_A, "name": "Lab"},
+            {"scope": "org", "role": "admin", "org_id": ORG_B, "name": "Shop"},
+        ]));
+        let (org_a, org_b): (OrgId, OrgId) = (ORG_A.parse().unwrap(), ORG_B.parse().unwrap());
+        assert!(resolver.check_write(&c, None, Some(&org_a), None).is_err());
+        assert!(resolver.check_write(&c, None, Some(&org_b), None).is_ok());
+    }
+}
@@ -0,0 +1,144 @@
+// Copyright 2020 Eishun Kondoh
+// SPDX-License-Identifier: Apache-2.0
+
+use rand::Rng;
+use std::time::Duration;
+
+/// When and how long `HttpClient` waits before sending a request again.
+///
+/// 429, 5xx, connection failures and timeouts are retried with
+/// exponential backoff, or after the delay of `Retry-After` when the
+/// response has one. GET, PUT and DELETE are retried; POST only when
+/// `retry_post` is set or the request is sent with `post_idempotent`.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    // attempts including the first one, 1 disables retries
+    pub max_attempts: u32,
+    // delay before the first retry, doubled for every following one
+    pub base_delay: Duration,
+    // upper bound of a delay, a longer `Retry-After` is not waited for
+    pub max_delay: Duration,
+    // randomize delays so that parallel jobs do not retry in lockstep
+    pub jitter: bool,
+    // retry every POST, not only the ones sent with `post_idempotent`
+    pub retry_post: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 4,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(60),
+            jitter: true,
+            retry_post: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn none() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Delay before the retry following `attempt` (1 for the first
+    /// request), `None` when the request must not be retried again.
+    pub fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+        if let Some(retry_after) = retry_after {
+            return if retry_after <= self.max_delay {
+                Some(retry_after)
+            } else {
+                None
+            };
+        }
+        let factor = 2u32.saturating_pow(attempt - 1);
+        let backoff = self
+            .base_delay
+            .checked_mul(factor)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay);
+        if self.jitter {
+            // "equal jitter": half of the backoff plus a random part
+            let half = backoff / 2;
+            let random = rand::thread_rng().gen_range(0, half.as_millis() as u64 + 1);
+            Some(half + Duration::from_millis(random))
+        } else {
+            Some(backoff)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(jitter: bool) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 10,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(8),
+            jitter,
+            retry_post: false,
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_max_delay() {
+        let p = policy(false);
+        let delays: Vec<u128> = (1..8)
+            .map(|attempt| p.delay(attempt, None).unwrap().as_millis())
+            .collect();
+        assert_eq!(delays, vec![500, 1000, 2000, 4000, 8000, 8000, 8000]);
+    }
+
+    #[test]
+    fn jitter_stays_within_half_and_full_backoff() {
+        let p = policy(true);
+        for attempt in 1..8 {
+            let full = policy(false).delay(attempt, None).unwrap();
+            for _ in 0..50 {
+                let delay = p.delay(attempt, None).unwrap();
+                assert!(full / 2 <= delay && delay <= full, "{:?}", delay);
+            }
+        }
+    }
+
+    #[test]
+    fn stops_after_max_attempts() {
+        let p = RetryPolicy {
+            max_attempts: 3,
+            ..policy(false)
+        };
+        assert!(p.delay(2, None).is_some());
+        assert!(p.delay(3, None).is_none());
+        assert!(p.delay(3, Some(Duration::from_secs(1))).is_none());
+        assert!(RetryPolicy::none().delay(1, None).is_none());
+    }
+
+    #[test]
+    fn retry_after_takes_precedence() {
+        let p = policy(true);
+        let retry_after = Duration::from_secs(3);
+        // neither backoff nor jitter apply
+        assert_eq!(p.delay(1, Some(retry_after)), Some(retry_after));
+        assert_eq!(p.delay(5, Some(Duration::ZERO)), Some(Duration::ZERO));
+        // a longer wait than max_delay is not retried at all
+        assert_eq!(p.delay(1, Some(Duration::from_secs(9))), None);
+        assert_eq!(p.delay(1, Some(p.max_delay)), Some(p.max_delay));
+    }
+
+    #[test]
+    fn huge_attempt_numbers_do_not_overflow() {
+        let p = RetryPolicy {
+            max_attempts: u32::MAX,
+            ..policy(false)
+        };
+        assert_eq!(p.delay(100, None), Some(p.max_delay));
+    }
+}
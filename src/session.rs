@@ -0,0 +1,273 @@
+// Copyright 2020 Eishun Kondoh
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::config::write_private;
+use crate::error::MistError;
+use crate::logger::{debug, warn};
+use reqwest::blocking::Request;
+use reqwest::header::{HeaderMap, HeaderValue, COOKIE, SET_COOKIE};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// header Mist expects on mutating requests of a cookie session
+pub const CSRF_HEADER: &'static str = "X-CSRFToken";
+
+// cookie names carry a suffix on some clouds, e.g. `csrftoken.eu`
+const CSRF_COOKIE: &'static str = "csrftoken";
+const SESSION_COOKIE: &'static str = "sessionid";
+
+/// Cookies of a password login, optionally kept in a file so that
+/// later invocations reuse the session.
+///
+/// The file is only read back for the API host it was written for.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Session {
+    base: String,
+    cookies: BTreeMap<String, Cookie>,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Cookie {
+    pub value: String,
+    // epoch seconds, None for cookies without Expires/Max-Age
+    #[serde(default)]
+    pub expires: Option<u64>,
+}
+
+impl Session {
+    /// In memory session for the API base URL
+    pub fn new<'a>(base: &'a str) -> Session {
+        Session {
+            base: base.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Session persisted to `path`. A missing, unreadable, expired or
+    /// other host's file starts an empty session.
+    pub fn load<'a>(base: &'a str, path: PathBuf) -> Session {
+        let mut session = match fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<Session>(&content).ok())
+        {
+            Some(session) if session.base == base => session,
+            Some(_) => {
+                debug("session file belongs to another host, ignoring it");
+                Session::new(base)
+            }
+            None => Session::new(base),
+        };
+        session.path = Some(path);
+        if session.purge(now()) && !session.is_logged_in() {
+            warn("login session expired, run `mist login` again");
+        }
+        session
+    }
+
+    /// Whether a session cookie is present and not expired
+    pub fn is_logged_in(&self) -> bool {
+        self.session_cookie()
+            .is_some_and(|cookie| cookie.expires.is_none_or(|t| t > now()))
+    }
+
+    /// Epoch seconds at which the login session expires
+    pub fn expires_at(&self) -> Option<u64> {
+        self.session_cookie().and_then(|cookie| cookie.expires)
+    }
+
+    pub fn csrf_token(&self) -> Option<&str> {
+        self.find(CSRF_COOKIE).map(|cookie| cookie.value.as_str())
+    }
+
+    /// Adds the `Cookie` header, and the CSRF header on mutating requests
+    pub fn apply(&self, request: &mut Request) {
+        let now = now();
+        let cookie = self
+            .cookies
+            .iter()
+            .filter(|(_, cookie)| cookie.expires.is_none_or(|t| t > now))
+            .map(|(name, cookie)| format!("{}={}", name, cookie.value))
+            .collect::<Vec<_>>()
+            .join("; ");
+        if let Ok(value) = HeaderValue::from_str(&cookie) {
+            if !cookie.is_empty() {
+                request.headers_mut().insert(COOKIE, value);
+            }
+        }
+        let mutating = matches!(
+            *request.method(),
+            Method::POST | Method::PUT | Method::PATCH | Method::DELETE
+        );
+        if let Some(token) = self.csrf_token().filter(|_| mutating) {
+            if let Ok(value) = HeaderValue::from_str(token) {
+                request.headers_mut().insert(CSRF_HEADER, value);
+            }
+        }
+    }
+
+    /// Records the `Set-Cookie` headers of a response, saving the session
+    /// file when a cookie changed
+    pub fn store(&mut self, headers: &HeaderMap) -> Result<(), MistError> {
+        let now = now();
+        let mut changed = false;
+        for value in headers.get_all(SET_COOKIE) {
+            if let Some((name, cookie)) = value.to_str().ok().and_then(|v| parse(v, now)) {
+                changed = true;
+                self.cookies.insert(name, cookie);
+            }
+        }
+        if changed {
+            self.purge(now);
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// Forgets the cookies and removes the session file
+    pub fn clear(&mut self) -> Result<(), MistError> {
+        self.cookies.clear();
+        if let Some(path) = &self.path {
+            match fs::remove_file(path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => debug(&format!("session file {} removed", path.display())),
+            }
+        }
+        Ok(())
+    }
+
+    // private functions
+
+    fn save(&self) -> Result<(), MistError> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let content = serde_json::to_string(self).map_err(|e| MistError::Invalid(e.to_string()))?;
+        // write then rename, so a concurrent command never reads half a file
+        let tmp = path.with_extension("tmp");
+        let _ = fs::remove_file(&tmp);
+        write_private(&tmp, &content)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Drops expired cookies, tells whether any was dropped
+    fn purge(&mut self, now: u64) -> bool {
+        let before = self.cookies.len();
+        self.cookies
+            .retain(|_, cookie| cookie.expires.is_none_or(|t| t > now));
+        self.cookies.len() != before
+    }
+
+    fn session_cookie(&self) -> Option<&Cookie> {
+        self.find(SESSION_COOKIE)
+    }
+
+    fn find<'a>(&self, prefix: &'a str) -> Option<&Cookie> {
+        self.cookies
+            .iter()
+            .find(|(name, _)| name.starts_with(prefix))
+            .map(|(_, cookie)| cookie)
+    }
+}
+
+// private functions
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::from_secs(0))
+        .as_secs()
+}
+
+/// `name=value; Expires=...; Max-Age=...; Path=/; ...`, Max-Age wins
+/// over Expires and a cookie being deleted gets an expiry in the past
+fn parse<'a>(set_cookie: &'a str, now: u64) -> Option<(String, Cookie)> {
+    let mut attrs = set_cookie.split(';');
+    let (name, value) = attrs.next()?.trim().split_once('=')?;
+    let mut expires = None;
+    let mut max_age = None;
+    for attr in attrs {
+        let (key, v) = match attr.trim().split_once('=') {
+            Some(kv) => kv,
+            None => continue,
+        };
+        if key.eq_ignore_ascii_case("max-age") {
+            max_age = v.trim().parse::<i64>().ok();
+        } else if key.eq_ignore_ascii_case("expires") {
+            expires = httpdate::parse_http_date(v.trim())
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs());
+        }
+    }
+    let expires = match max_age {
+        Some(age) if age <= 0 => Some(0),
+        Some(age) => Some(now + age as u64),
+        None => expires,
+    };
+    Some((
+        name.trim().to_string(),
+        Cookie {
+            value: value.trim().trim_matches('"').to_string(),
+            expires,
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_max_age_wins_over_expires() {
+        let (name, cookie) = parse(
+            "sessionid=abc; expires=Wed, 21 Oct 2015 07:28:00 GMT; Max-Age=3600; Path=/",
+            1000,
+        )
+        .unwrap();
+        assert_eq!(name, "sessionid");
+        assert_eq!(cookie.value, "abc");
+        assert_eq!(cookie.expires, Some(4600));
+    }
+
+    #[test]
+    fn parse_expires() {
+        let (_, cookie) = parse("csrftoken=x; Expires=Wed, 21 Oct 2015 07:28:00 GMT", 0).unwrap();
+        assert_eq!(cookie.expires, Some(1_445_412_480));
+    }
+
+    #[test]
+    fn parse_deleted_and_session_cookies() {
+        let (_, cookie) = parse("sessionid=\"\"; Max-Age=0; Path=/", 1000).unwrap();
+        assert_eq!(cookie.value, "");
+        assert_eq!(cookie.expires, Some(0));
+        let (_, cookie) = parse("sessionid=abc; Max-Age=-1", 1000).unwrap();
+        assert_eq!(cookie.expires, Some(0));
+        let (_, cookie) = parse(" csrftoken = \"tok\" ; HttpOnly; Secure", 1000).unwrap();
+        assert_eq!(cookie.value, "tok");
+        assert_eq!(cookie.expires, None);
+    }
+
+    #[test]
+    fn parse_ignores_bad_attributes() {
+        let (_, cookie) = parse("sessionid=abc; Max-Age=soon; Expires=tomorrow", 1000).unwrap();
+        assert_eq!(cookie.expires, None);
+    }
+
+    #[test]
+    fn parse_rejects_cookies_without_value() {
+        assert!(parse("", 0).is_none());
+        assert!(parse("sessionid", 0).is_none());
+        assert!(parse("; Path=/", 0).is_none());
+    }
+}
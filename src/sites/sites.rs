@@ -1,18 +1,29 @@
 // Copyright 2020 Eishun Kondoh
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::consts::MIST_API_BASE;
+use crate::error::MistError;
 use crate::http::HttpClient;
 use crate::logger::{debug, warn};
+use crate::output::Render;
+use crate::types::{MspId, OrgId, SiteId};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Sites(Vec<Site>);
 
-#[derive(Serialize, Deserialize, Debug)]
+/// ref: https://api.mist.com/api/v1/docs/Site#site
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub struct Site {
+    // id, org_id and timestamps are assigned by the API
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<SiteId>,
     pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org_id: Option<OrgId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub msp_id: Option<MspId>,
     #[serde(default)]
     pub timezone: Option<String>,
     #[serde(default)]
@@ -24,6 +35,12 @@ pub struct Site {
     #[serde(default)]
     pub networktemplate_id: Option<String>,
     #[serde(default)]
+    pub rftemplate_id: Option<String>,
+    #[serde(default)]
+    pub aptemplate_id: Option<String>,
+    #[serde(default)]
+    pub gatewaytemplate_id: Option<String>,
+    #[serde(default)]
     pub latlng: Option<HashMap<String, f64>>,
     #[serde(default)]
     pub sitegroup_ids: Option<Vec<String>>,
@@ -31,12 +48,165 @@ pub struct Site {
     pub address: Option<String>,
     #[serde(default)]
     pub notes: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tzoffset: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_time: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified_time: Option<u32>,
+}
+
+/// Site settings. Every field is optional so that an update only changes
+/// the ones given; fields without a type here are kept in `other`.
+///
+/// ref: https://api.mist.com/api/v1/docs/Site#site-setting
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct SiteSetting {
+    // id, site_id, org_id and timestamps are assigned by the API
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub site_id: Option<SiteId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org_id: Option<OrgId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_time: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified_time: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rtsa: Option<Rtsa>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub led: Option<Led>,
+    // site variables used in templates, e.g. `{{vlan_id}}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vars: Option<BTreeMap<String, String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_upgrade: Option<AutoUpgrade>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_push_policy: Option<ConfigPushPolicy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wifi: Option<WifiSetting>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ble_config: Option<BleConfig>,
+    #[serde(flatten)]
+    pub other: BTreeMap<String, Value>,
+}
+
+/// real-time location
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Rtsa {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub track_asset: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub app_waking: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disable_dead_reckoning: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disable_pressure_sensor: Option<bool>,
+}
+
+/// AP status LED
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Led {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    // 0-255
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub brightness: Option<u8>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct AutoUpgrade {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    // stable, beta or custom
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    // e.g. 02:00
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_of_day: Option<String>,
+    // any, sun, mon, ...
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub day_of_week: Option<String>,
+    // firmware version by model when version is custom
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_versions: Option<BTreeMap<String, String>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ConfigPushPolicy {
+    // stop pushing config changes to the devices
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_push: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub push_window: Option<PushWindow>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct PushWindow {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    // hours by day, e.g. {"mon": "09:00-17:00"}
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hours: Option<BTreeMap<String, String>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct WifiSetting {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disable_11k: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enable_arp_spoof_check: Option<bool>,
+    // default, enabled or disabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy_arp: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cisco_enabled: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mesh_enabled: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locate_connected: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locate_unconnected: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct BleConfig {
+    // default or custom
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub power_mode: Option<String>,
+    // dBm when power_mode is custom
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub power: Option<i8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub beacon_enabled: Option<bool>,
+    // beacons per second
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub beacon_rate: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ibeacon_enabled: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ibeacon_uuid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ibeacon_major: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ibeacon_minor: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eddystone_uid_enabled: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eddystone_url_enabled: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eddystone_url_url: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct SiteStats {
     pub name: String,
-    pub id: String,
+    pub id: SiteId,
     #[serde(default)]
     pub address: Option<String>,
     #[serde(default)]
@@ -54,7 +224,7 @@ pub struct SiteStats {
     #[serde(default)]
     pub modified_time: Option<u32>,
     #[serde(default)]
-    pub msp_id: Option<String>,
+    pub msp_id: Option<MspId>,
     #[serde(default)]
     pub networktemplate_id: Option<String>,
     #[serde(default)]
@@ -76,7 +246,7 @@ pub struct SiteStats {
     #[serde(default)]
     pub num_switch_connected: u16,
     #[serde(default)]
-    pub org_id: Option<String>,
+    pub org_id: Option<OrgId>,
     #[serde(default)]
     pub rftemplate_id: Option<String>,
     #[serde(default)]
@@ -92,176 +262,366 @@ pub struct SiteGroups(Vec<SiteGroup>);
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct SiteGroup {
-    pub site_ids: Vec<String>,
-    pub id: String,
+    #[serde(default)]
+    pub site_ids: Vec<SiteId>,
+    // id, org_id and timestamps are assigned by the API
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
     pub name: String,
-    pub org_id: String,
-    pub created_time: u32,
-    pub modified_time: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org_id: Option<OrgId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_time: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified_time: Option<u32>,
+}
+
+impl Sites {
+    pub fn iter(&self) -> impl Iterator<Item = &Site> {
+        self.0.iter()
+    }
+}
+
+impl From<Vec<Site>> for Sites {
+    fn from(v: Vec<Site>) -> Self {
+        Sites(v)
+    }
+}
+
+impl SiteGroups {
+    pub fn iter(&self) -> impl Iterator<Item = &SiteGroup> {
+        self.0.iter()
+    }
+}
+
+impl From<Vec<SiteGroup>> for SiteGroups {
+    fn from(v: Vec<SiteGroup>) -> Self {
+        SiteGroups(v)
+    }
 }
 
-pub fn list_group<'a>(c: &HttpClient, org_id: &'a str) -> Result<SiteGroups, ()> {
-    match c.get(site_groups_path(org_id), &()) {
-        Ok(Some(sitegroups)) => {
+impl Render for Sites {
+    type Record = Site;
+
+    fn default_columns() -> &'static [&'static str] {
+        &["id", "name", "country_code", "timezone", "address"]
+    }
+
+    fn records(&self) -> Vec<&Site> {
+        self.0.iter().collect()
+    }
+}
+
+impl Render for Site {
+    type Record = Site;
+
+    fn default_columns() -> &'static [&'static str] {
+        &["id", "name", "country_code", "timezone", "address"]
+    }
+
+    fn records(&self) -> Vec<&Site> {
+        vec![self]
+    }
+}
+
+impl Render for SiteSetting {
+    type Record = SiteSetting;
+
+    fn default_columns() -> &'static [&'static str] {
+        &["site_id", "vars", "auto_upgrade", "config_push_policy"]
+    }
+
+    fn records(&self) -> Vec<&SiteSetting> {
+        vec![self]
+    }
+}
+
+impl Render for SiteStats {
+    type Record = SiteStats;
+
+    fn default_columns() -> &'static [&'static str] {
+        &[
+            "id",
+            "name",
+            "num_ap",
+            "num_ap_connected",
+            "num_switch",
+            "num_switch_connected",
+            "num_gateway",
+            "num_gateway_connected",
+            "num_clients",
+        ]
+    }
+
+    fn records(&self) -> Vec<&SiteStats> {
+        vec![self]
+    }
+}
+
+impl Render for SiteGroups {
+    type Record = SiteGroup;
+
+    fn default_columns() -> &'static [&'static str] {
+        &["id", "name", "site_ids"]
+    }
+
+    fn records(&self) -> Vec<&SiteGroup> {
+        self.0.iter().collect()
+    }
+}
+
+impl Render for SiteGroup {
+    type Record = SiteGroup;
+
+    fn default_columns() -> &'static [&'static str] {
+        &["id", "name", "site_ids"]
+    }
+
+    fn records(&self) -> Vec<&SiteGroup> {
+        vec![self]
+    }
+}
+
+pub fn list_group<'a>(c: &HttpClient, org_id: &'a OrgId) -> Result<SiteGroups, MistError> {
+    match c.get(site_groups_path(c, org_id), &()) {
+        Ok(sitegroups) => {
             debug("list site groups request succeed");
             Ok(sitegroups)
         }
-        _ => {
-            debug("list site groups request failed");
-            Err(())
+        Err(e) => {
+            debug(&format!("list site groups request failed: {}", e));
+            Err(e)
         }
     }
 }
 
-pub fn get_group<'a>(c: &HttpClient, org_id: &'a str, group_id: &'a str) -> Result<SiteGroup, ()> {
-    match c.get(site_group_path(org_id, group_id), &()) {
-        Ok(Some(sitegroup)) => {
+/// Lists every site group, following all pages
+pub fn list_group_all<'a>(c: &HttpClient, org_id: &'a OrgId) -> Result<SiteGroups, MistError> {
+    match c.get_all(site_groups_path(c, org_id)) {
+        Ok(sitegroups) => {
+            debug("list all site groups request succeed");
+            Ok(SiteGroups(sitegroups))
+        }
+        Err(e) => {
+            debug(&format!("list all site groups request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+pub fn get_group<'a>(
+    c: &HttpClient,
+    org_id: &'a OrgId,
+    group_id: &'a str,
+) -> Result<SiteGroup, MistError> {
+    match c.get(site_group_path(c, org_id, group_id), &()) {
+        Ok(sitegroup) => {
             debug("get site groups request succeed");
             Ok(sitegroup)
         }
-        _ => {
-            debug("get site groups request failed");
-            Err(())
+        Err(e) => {
+            debug(&format!("get site groups request failed: {}", e));
+            Err(e)
         }
     }
 }
 
 pub fn create_group<'a>(
     c: &HttpClient,
-    org_id: &'a str,
+    org_id: &'a OrgId,
     group: &SiteGroup,
-) -> Result<SiteGroup, ()> {
-    match c.post(site_groups_path(org_id), &group) {
-        Ok(Some(sitegroup)) => {
+) -> Result<SiteGroup, MistError> {
+    match c.post(site_groups_path(c, org_id), &group) {
+        Ok(sitegroup) => {
             debug("create site groups request succeed");
             Ok(sitegroup)
         }
-        _ => {
-            debug("create site groups request failed");
-            Err(())
+        Err(e) => {
+            debug(&format!("create site groups request failed: {}", e));
+            Err(e)
         }
     }
 }
 
 pub fn update_group<'a>(
     c: &HttpClient,
-    org_id: &'a str,
+    org_id: &'a OrgId,
     group_id: &'a str,
     group: &SiteGroup,
-) -> Result<SiteGroup, ()> {
-    match c.put(site_group_path(org_id, group_id), &group) {
-        Ok(Some(sitegroup)) => {
+) -> Result<SiteGroup, MistError> {
+    match c.put(site_group_path(c, org_id, group_id), &group) {
+        Ok(sitegroup) => {
             debug("update site groups request succeed");
             Ok(sitegroup)
         }
-        _ => {
-            debug("update site groups request failed");
-            Err(())
+        Err(e) => {
+            debug(&format!("update site groups request failed: {}", e));
+            Err(e)
         }
     }
 }
 
-pub fn delete_group<'a>(c: &HttpClient, org_id: &'a str, group_id: &'a str) -> Result<(), ()> {
-    match c.delete(site_group_path(org_id, group_id), &()) {
-        Ok(Some(_)) => {
+pub fn delete_group<'a>(
+    c: &HttpClient,
+    org_id: &'a OrgId,
+    group_id: &'a str,
+) -> Result<(), MistError> {
+    match c.delete(site_group_path(c, org_id, group_id), &()) {
+        Ok(_) => {
             debug("get site groups request succeed");
             Ok(())
         }
-        _ => {
-            debug("get site groups request failed");
-            Err(())
+        Err(e) => {
+            debug(&format!("get site groups request failed: {}", e));
+            Err(e)
         }
     }
 }
 
-pub fn get_stats<'a>(c: &HttpClient, site_id: &'a str) -> Result<SiteStats, ()> {
-    match c.get(site_stats_path(site_id), &()) {
-        Ok(Some(sites)) => {
+pub fn get_stats<'a>(c: &HttpClient, site_id: &'a SiteId) -> Result<SiteStats, MistError> {
+    match c.get(site_stats_path(c, site_id), &()) {
+        Ok(sites) => {
             debug("get site stats request succeed");
             Ok(sites)
         }
-        _ => {
-            warn("get site stats request failed");
-            Err(())
+        Err(e) => {
+            warn(&format!("get site stats request failed: {}", e));
+            Err(e)
         }
     }
 }
 
-pub fn list<'a>(c: &HttpClient, org_id: &'a str) -> Result<Sites, ()> {
-    match c.get(sites_path(org_id), &()) {
-        Ok(Some(sites)) => {
+pub fn list<'a>(c: &HttpClient, org_id: &'a OrgId) -> Result<Sites, MistError> {
+    match c.get(sites_path(c, org_id), &()) {
+        Ok(sites) => {
             debug("list sites request succeed");
             Ok(sites)
         }
-        _ => {
-            warn("list sites request failed");
-            Err(())
+        Err(e) => {
+            warn(&format!("list sites request failed: {}", e));
+            Err(e)
         }
     }
 }
 
-pub fn get<'a>(c: &HttpClient, site_id: &'a str) -> Result<Site, ()> {
-    match c.get(site_path(site_id), &()) {
-        Ok(Some(site)) => {
+/// Lists every site, following all pages
+pub fn list_all<'a>(c: &HttpClient, org_id: &'a OrgId) -> Result<Sites, MistError> {
+    match c.get_all(sites_path(c, org_id)) {
+        Ok(sites) => {
+            debug("list all sites request succeed");
+            Ok(Sites(sites))
+        }
+        Err(e) => {
+            warn(&format!("list all sites request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+pub fn get<'a>(c: &HttpClient, site_id: &'a SiteId) -> Result<Site, MistError> {
+    match c.get(site_path(c, site_id), &()) {
+        Ok(site) => {
             debug("get site request succeed");
             Ok(site)
         }
-        _ => {
-            warn("get site request failed");
-            Err(())
+        Err(e) => {
+            warn(&format!("get site request failed: {}", e));
+            Err(e)
         }
     }
 }
 
-pub fn create<'a>(c: &HttpClient, org_id: &'a str, site: &Site) -> Result<Site, ()> {
-    match c.post(sites_path(org_id), site) {
-        Ok(Some(site)) => {
+pub fn create<'a>(c: &HttpClient, org_id: &'a OrgId, site: &Site) -> Result<Site, MistError> {
+    match c.post(sites_path(c, org_id), site) {
+        Ok(site) => {
             debug("site creation is succeed");
             Ok(site)
         }
-        _ => {
-            warn("site creation is failed");
-            Err(())
+        Err(e) => {
+            warn(&format!("site creation is failed: {}", e));
+            Err(e)
         }
     }
 }
 
-pub fn update<'a>(c: &HttpClient, site_id: &'a str, site: &Site) -> Result<Site, ()> {
-    match c.put(site_path(site_id), site) {
-        Ok(Some(site)) => {
+pub fn update<'a>(c: &HttpClient, site_id: &'a SiteId, site: &Site) -> Result<Site, MistError> {
+    match c.put(site_path(c, site_id), site) {
+        Ok(site) => {
             debug("site modification is succeed");
             Ok(site)
         }
-        _ => {
-            warn("site modification is failed");
-            Err(())
+        Err(e) => {
+            warn(&format!("site modification is failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+/// Deletes a site. Its devices go back to the inventory unassigned.
+pub fn delete<'a>(c: &HttpClient, site_id: &'a SiteId) -> Result<(), MistError> {
+    match c.delete(site_path(c, site_id), &()) {
+        Ok(_) => {
+            debug("site deletion is succeed");
+            Ok(())
+        }
+        Err(e) => {
+            warn(&format!("site deletion is failed: {}", e));
+            Err(e)
         }
     }
 }
 
-pub fn delete<'a>(c: &HttpClient, site_id: &'a str) -> Result<(), ()> {
-    match c.delete(site_path(site_id), &()) {
-        Ok(_) => Ok(()),
-        Err(_) => Err(()),
+pub fn get_setting<'a>(c: &HttpClient, site_id: &'a SiteId) -> Result<SiteSetting, MistError> {
+    match c.get(site_setting_path(c, site_id), &()) {
+        Ok(setting) => {
+            debug("site setting request succeed");
+            Ok(setting)
+        }
+        Err(e) => {
+            warn(&format!("site setting request failed: {}", e));
+            Err(e)
+        }
     }
 }
 
-pub fn site_path<'a>(site_id: &'a str) -> String {
-    format!("{}/sites/{}/stats", MIST_API_BASE, site_id)
+/// Changes the settings given in `setting`, the others are left as is
+pub fn update_setting<'a>(
+    c: &HttpClient,
+    site_id: &'a SiteId,
+    setting: &'a SiteSetting,
+) -> Result<SiteSetting, MistError> {
+    match c.put(site_setting_path(c, site_id), setting) {
+        Ok(setting) => {
+            debug("site setting change request succeed");
+            Ok(setting)
+        }
+        Err(e) => {
+            warn(&format!("site setting change request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+pub fn site_path<'a>(c: &HttpClient, site_id: &'a SiteId) -> String {
+    c.url(&format!("sites/{}", site_id))
+}
+
+pub fn site_setting_path<'a>(c: &HttpClient, site_id: &'a SiteId) -> String {
+    c.url(&format!("sites/{}/setting", site_id))
 }
 
-pub fn site_stats_path<'a>(site_id: &'a str) -> String {
-    format!("{}/sites/{}/stats", MIST_API_BASE, site_id)
+pub fn site_stats_path<'a>(c: &HttpClient, site_id: &'a SiteId) -> String {
+    c.url(&format!("sites/{}/stats", site_id))
 }
 
-pub fn sites_path<'a>(org_id: &'a str) -> String {
-    format!("{}/orgs/{}/sites", MIST_API_BASE, org_id)
+pub fn sites_path<'a>(c: &HttpClient, org_id: &'a OrgId) -> String {
+    c.url(&format!("orgs/{}/sites", org_id))
 }
 
-pub fn site_groups_path<'a>(org_id: &'a str) -> String {
-    format!("{}/orgs/{}/sitegroups", MIST_API_BASE, org_id)
+pub fn site_groups_path<'a>(c: &HttpClient, org_id: &'a OrgId) -> String {
+    c.url(&format!("orgs/{}/sitegroups", org_id))
 }
 
-pub fn site_group_path<'a>(org_id: &'a str, group_id: &'a str) -> String {
-    format!("{}/orgs/{}/sitegroups/{}", MIST_API_BASE, org_id, group_id)
+pub fn site_group_path<'a>(c: &HttpClient, org_id: &'a OrgId, group_id: &'a str) -> String {
+    c.url(&format!("orgs/{}/sitegroups/{}", org_id, group_id))
 }
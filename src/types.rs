@@ -0,0 +1,377 @@
+// Copyright 2020 Eishun Kondoh
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::error::MistError;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+/// IDs of Mist resources, one type per resource so that an org ID can
+/// not be passed where a site ID is expected.
+///
+/// `FromStr` checks the UUID form, `new` takes any string as is.
+macro_rules! id_type {
+    ($(#[$meta:meta])* $name:ident, $kind:expr) => {
+        $(#[$meta])*
+        #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        #[serde(transparent)]
+        pub struct $name(String);
+
+        impl $name {
+            pub fn new<'a>(id: &'a str) -> Self {
+                $name(id.to_string())
+            }
+
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = MistError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                if is_uuid(s) {
+                    Ok($name(s.to_ascii_lowercase()))
+                } else {
+                    Err(MistError::Invalid(format!("malformed {} id: {}", $kind, s)))
+                }
+            }
+        }
+
+        impl PartialEq<str> for $name {
+            fn eq(&self, other: &str) -> bool {
+                self.0 == other
+            }
+        }
+    };
+}
+
+/// String valued fields of the API as enums. Values added to the API
+/// later deserialize to `Unknown` instead of failing, while `FromStr`,
+/// used for command line arguments, only accepts the known ones.
+macro_rules! string_enum {
+    ($(#[$meta:meta])* $name:ident, $kind:expr, default = $default:ident, $values:tt) => {
+        string_enum!($(#[$meta])* $name, $kind, $values);
+
+        impl Default for $name {
+            fn default() -> Self {
+                $name::$default
+            }
+        }
+    };
+    ($(#[$meta:meta])* $name:ident, $kind:expr, { $($variant:ident => $value:expr),* $(,)? }) => {
+        $(#[$meta])*
+        #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+        #[serde(from = "String", into = "String")]
+        pub enum $name {
+            $($variant,)*
+            Unknown(String),
+        }
+
+        impl $name {
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $($name::$variant => $value,)*
+                    $name::Unknown(s) => s,
+                }
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(s: String) -> Self {
+                match s.as_str() {
+                    $($value => $name::$variant,)*
+                    _ => $name::Unknown(s),
+                }
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(v: $name) -> Self {
+                v.as_str().to_string()
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = MistError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                match $name::from(s.to_ascii_lowercase()) {
+                    $name::Unknown(_) => Err(MistError::Invalid(format!(
+                        "unknown {}: {}, expected one of: {}",
+                        $kind,
+                        s,
+                        [$($value),*].join(", ")
+                    ))),
+                    v => Ok(v),
+                }
+            }
+        }
+    };
+}
+
+id_type!(OrgId, "org");
+id_type!(SiteId, "site");
+id_type!(DeviceId, "device");
+id_type!(MspId, "msp");
+
+string_enum!(DeviceType, "device type", {
+    Ap => "ap",
+    Switch => "switch",
+    Gateway => "gateway",
+});
+
+string_enum!(
+    /// event stream a webhook delivers
+    WebhookTopic, "webhook topic", {
+    Alarms => "alarms",
+    Audits => "audits",
+    DeviceEvents => "device-events",
+    ClientJoin => "client-join",
+    Location => "location",
+    Zone => "zone",
+    AssetRaw => "asset-raw",
+});
+
+string_enum!(
+    /// role of a privilege or API token
+    Role, "role", {
+    Admin => "admin",
+    Write => "write",
+    Read => "read",
+    Helpdesk => "helpdesk",
+    Installer => "installer",
+});
+
+string_enum!(
+    /// what a privilege or API token applies to
+    Scope, "scope", {
+    Msp => "msp",
+    Orggroup => "orggroup",
+    Org => "org",
+    Sitegroup => "sitegroup",
+    Site => "site",
+});
+
+string_enum!(SyslogProtocol, "syslog protocol", default = Udp, {
+    Udp => "udp",
+    Tcp => "tcp",
+});
+
+string_enum!(Severity, "syslog severity", default = Any, {
+    Any => "any",
+    Emergency => "emerg",
+    Alert => "alert",
+    Critical => "critical",
+    Error => "error",
+    Warning => "warning",
+    Notice => "notice",
+    Info => "info",
+});
+
+string_enum!(Facility, "syslog facility", default = Any, {
+    Any => "any",
+    Kernel => "kernel",
+    User => "user",
+    Mail => "mail",
+    Daemon => "daemon",
+    Security => "security",
+    Syslog => "syslog",
+    Lpr => "lpr",
+    News => "news",
+    Uucp => "uucp",
+    Clock => "clock",
+    Authpriv => "authpriv",
+    Ftp => "ftp",
+    Ntp => "ntp",
+    Audit => "audit",
+    Alert => "alert",
+    Cron => "cron",
+    Local0 => "local0",
+    Local1 => "local1",
+    Local2 => "local2",
+    Local3 => "local3",
+    Local4 => "local4",
+    Local5 => "local5",
+    Local6 => "local6",
+    Local7 => "local7",
+});
+
+/// MAC address in the bare lowercase form of the API, `5c5b35aabbcc`.
+/// Parsing also accepts `5c:5b:35:aa:bb:cc`, `5c-5b-35-aa-bb-cc` and
+/// `5c5b.35aa.bbcc` in any case.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(try_from = "String", into = "String")]
+pub struct MacAddr(String);
+
+impl Role {
+    /// roles which can not change an org or site
+    pub fn is_read_only(&self) -> bool {
+        matches!(self, Role::Read | Role::Helpdesk)
+    }
+}
+
+impl DeviceId {
+    /// ID the API gives the device with `mac`
+    pub fn from_mac<'a>(mac: &'a MacAddr) -> DeviceId {
+        DeviceId(format!("00000000-0000-0000-1000-{}", mac))
+    }
+}
+
+impl MacAddr {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// `5c:5b:35:aa:bb:cc`
+    pub fn colon(&self) -> String {
+        self.0
+            .as_bytes()
+            .chunks(2)
+            .map(|pair| String::from_utf8_lossy(pair).into_owned())
+            .collect::<Vec<_>>()
+            .join(":")
+    }
+}
+
+impl fmt::Display for MacAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for MacAddr {
+    type Err = MistError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim();
+        let hex = match [(':', 2), ('-', 2), ('.', 4)]
+            .iter()
+            .find(|(sep, _)| trimmed.contains(*sep))
+        {
+            Some((sep, width)) => {
+                let groups: Vec<&str> = trimmed.split(*sep).collect();
+                if groups.iter().all(|group| group.len() == *width) {
+                    groups.concat()
+                } else {
+                    String::new()
+                }
+            }
+            None => trimmed.to_string(),
+        };
+        if hex.len() == 12 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            Ok(MacAddr(hex.to_ascii_lowercase()))
+        } else {
+            Err(MistError::Invalid(format!("malformed MAC address: {}", s)))
+        }
+    }
+}
+
+impl TryFrom<String> for MacAddr {
+    type Error = MistError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<MacAddr> for String {
+    fn from(mac: MacAddr) -> Self {
+        mac.0
+    }
+}
+
+/// Whether `s` is a UUID such as Mist IDs, e.g.
+/// `4ac1dcf4-9d8b-7211-65c4-057819f0862b`
+pub fn is_uuid<'a>(s: &'a str) -> bool {
+    let groups: Vec<&str> = s.split('-').collect();
+    groups.len() == 5
+        && groups
+            .iter()
+            .zip([8, 4, 4, 4, 12].iter())
+            .all(|(g, len)| g.len() == *len && g.chars().all(|ch| ch.is_ascii_hexdigit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mac_accepts_separators_and_case() {
+        for s in [
+            "5c5b35aabbcc",
+            "5C:5B:35:AA:BB:CC",
+            "5c-5b-35-aa-bb-cc",
+            "5c5b.35aa.bbcc",
+            " 5c:5b:35:aa:bb:cc ",
+        ] {
+            let mac: MacAddr = s.parse().unwrap();
+            assert_eq!(mac.as_str(), "5c5b35aabbcc", "{}", s);
+        }
+    }
+
+    #[test]
+    fn mac_rejects_malformed() {
+        for s in [
+            "",
+            "5c5b35aabbc",
+            "5c5b35aabbccdd",
+            "5c:5b:35:aa:bb:cg",
+            "5c 5b 35 aa bb cc",
+            "5c:5b3:5aa-bbcc",
+            "5c:5b:35-aa-bb-cc",
+            "5c5b:35aa:bbcc",
+            "5c.5b.35.aa.bb.cc",
+            "5c5b35.aabbcc",
+            "5c:5b:35:aa:bb:cc:",
+            ":5c:5b:35:aa:bb:cc",
+        ] {
+            assert!(s.parse::<MacAddr>().is_err(), "{}", s);
+        }
+        assert!(serde_json::from_str::<MacAddr>("\"5c:5b:35\"").is_err());
+    }
+
+    #[test]
+    fn mac_deserializes_normalized() {
+        let mac: MacAddr = serde_json::from_str("\"5C:5B:35:AA:BB:CC\"").unwrap();
+        assert_eq!(serde_json::to_string(&mac).unwrap(), "\"5c5b35aabbcc\"");
+    }
+
+    #[test]
+    fn id_parses_uuid() {
+        let id: OrgId = "4AC1DCF4-9D8B-7211-65C4-057819F0862B".parse().unwrap();
+        assert_eq!(id.as_str(), "4ac1dcf4-9d8b-7211-65c4-057819f0862b");
+        assert!(is_uuid("4ac1dcf4-9d8b-7211-65c4-057819f0862b"));
+    }
+
+    #[test]
+    fn id_rejects_malformed() {
+        for s in [
+            "",
+            "Tokyo",
+            "4ac1dcf4-9d8b-7211-65c4",
+            "4ac1dcf4-9d8b-7211-65c4-057819f0862",
+            "4ac1dcf49d8b721165c4057819f0862b",
+            "4ac1dcf4-9d8b-7211-65c4-057819f0862g",
+            "4ac1dcf4-9d8b-7211-65c4-057819f0862b-0",
+        ] {
+            assert!(s.parse::<SiteId>().is_err(), "{}", s);
+        }
+        // new takes names and such as they are
+        assert_eq!(SiteId::new("Tokyo").as_str(), "Tokyo");
+    }
+}
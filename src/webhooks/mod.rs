@@ -0,0 +1,4 @@
+// Copyright 2020 Eishun Kondoh
+// SPDX-License-Identifier: Apache-2.0
+
+pub mod webhooks;
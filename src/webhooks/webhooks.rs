@@ -0,0 +1,226 @@
+// Copyright 2020 Eishun Kondoh
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::error::MistError;
+use crate::http::HttpClient;
+use crate::logger::{debug, info, warn};
+use crate::output::Render;
+use crate::types::{OrgId, SiteId, WebhookTopic};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+///
+/// ref: https://api.mist.com/api/v1/docs/Org#webhooks
+///
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Webhooks(Vec<Webhook>);
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Webhook {
+    // id, owner and timestamps are assigned by the API
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub name: String,
+    pub url: String,
+    // signs the deliveries with an X-Mist-Signature header
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    #[serde(default)]
+    pub topics: Vec<WebhookTopic>,
+    #[serde(default = "default_enabled")]
+    pub verify_cert: bool,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org_id: Option<OrgId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub site_id: Option<SiteId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_time: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified_time: Option<u32>,
+}
+
+/// org or site the webhooks belong to
+#[derive(Debug, Clone, PartialEq)]
+pub enum WebhookOwner {
+    Org(OrgId),
+    Site(SiteId),
+}
+
+impl Webhook {
+    /// An enabled webhook verifying the certificate of `url`
+    pub fn new<'a>(name: &'a str, url: &'a str, topics: Vec<WebhookTopic>) -> Self {
+        Webhook {
+            id: None,
+            name: name.to_string(),
+            url: url.to_string(),
+            secret: None,
+            topics,
+            verify_cert: true,
+            enabled: true,
+            org_id: None,
+            site_id: None,
+            created_time: None,
+            modified_time: None,
+        }
+    }
+}
+
+impl fmt::Display for WebhookOwner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WebhookOwner::Org(org_id) => write!(f, "orgs/{}", org_id),
+            WebhookOwner::Site(site_id) => write!(f, "sites/{}", site_id),
+        }
+    }
+}
+
+impl From<Vec<Webhook>> for Webhooks {
+    fn from(v: Vec<Webhook>) -> Self {
+        Webhooks(v)
+    }
+}
+
+impl Render for Webhooks {
+    type Record = Webhook;
+
+    fn default_columns() -> &'static [&'static str] {
+        &["id", "name", "url", "topics", "enabled"]
+    }
+
+    fn records(&self) -> Vec<&Webhook> {
+        self.0.iter().collect()
+    }
+}
+
+impl Render for Webhook {
+    type Record = Webhook;
+
+    fn default_columns() -> &'static [&'static str] {
+        &["id", "name", "url", "topics", "verify_cert", "enabled"]
+    }
+
+    fn records(&self) -> Vec<&Webhook> {
+        vec![self]
+    }
+}
+
+pub fn list<'a>(c: &HttpClient, owner: &'a WebhookOwner) -> Result<Webhooks, MistError> {
+    match c.get(webhooks_path(c, owner), &()) {
+        Ok(webhooks) => {
+            debug("list webhooks request succeed");
+            Ok(webhooks)
+        }
+        Err(e) => {
+            warn(&format!("list webhooks request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+pub fn get<'a>(
+    c: &HttpClient,
+    owner: &'a WebhookOwner,
+    webhook_id: &'a str,
+) -> Result<Webhook, MistError> {
+    match c.get(webhook_path(c, owner, webhook_id), &()) {
+        Ok(webhook) => {
+            debug("get webhook request succeed");
+            Ok(webhook)
+        }
+        Err(e) => {
+            warn(&format!("get webhook request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+pub fn create<'a>(
+    c: &HttpClient,
+    owner: &'a WebhookOwner,
+    webhook: &'a Webhook,
+) -> Result<Webhook, MistError> {
+    match c.post(webhooks_path(c, owner), webhook) {
+        Ok(webhook) => {
+            debug("create webhook request succeed");
+            Ok(webhook)
+        }
+        Err(e) => {
+            warn(&format!("create webhook request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+pub fn update<'a>(
+    c: &HttpClient,
+    owner: &'a WebhookOwner,
+    webhook_id: &'a str,
+    webhook: &'a Webhook,
+) -> Result<Webhook, MistError> {
+    match c.put(webhook_path(c, owner, webhook_id), webhook) {
+        Ok(webhook) => {
+            debug("update webhook request succeed");
+            Ok(webhook)
+        }
+        Err(e) => {
+            warn(&format!("update webhook request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+pub fn delete<'a>(
+    c: &HttpClient,
+    owner: &'a WebhookOwner,
+    webhook_id: &'a str,
+) -> Result<(), MistError> {
+    match c.delete(webhook_path(c, owner, webhook_id), &()) {
+        Ok(_) => {
+            debug("delete webhook request succeed");
+            Ok(())
+        }
+        Err(e) => {
+            warn(&format!("delete webhook request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+/// Asks the API to send a test delivery to the webhook
+pub fn ping<'a>(
+    c: &HttpClient,
+    owner: &'a WebhookOwner,
+    webhook_id: &'a str,
+) -> Result<(), MistError> {
+    let url = format!("{}/ping", webhook_path(c, owner, webhook_id));
+    match c.post::<_, serde_json::Value>(url, &()) {
+        Ok(_) => {
+            info(&format!(
+                "test delivery of webhook {} requested",
+                webhook_id
+            ));
+            Ok(())
+        }
+        Err(e) => {
+            warn(&format!("ping webhook request failed: {}", e));
+            Err(e)
+        }
+    }
+}
+
+// private functions
+
+fn webhooks_path<'a>(c: &HttpClient, owner: &'a WebhookOwner) -> String {
+    c.url(&format!("{}/webhooks", owner))
+}
+
+fn webhook_path<'a>(c: &HttpClient, owner: &'a WebhookOwner, webhook_id: &'a str) -> String {
+    c.url(&format!("{}/webhooks/{}", owner, webhook_id))
+}
+
+fn default_enabled() -> bool {
+    true
+}